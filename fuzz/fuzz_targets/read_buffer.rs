@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ufwt::file::{ExtractMode, UnidenFirmware};
+use ufwt::lint::lint;
+
+fuzz_target!(|data: &[u8]| {
+    for lenient in [false, true] {
+        let mut firmware = UnidenFirmware::from_bytes(data);
+        firmware.lenient = lenient;
+        if firmware.read_buffer().is_err() {
+            continue;
+        }
+        for file in firmware.files() {
+            for mode in [ExtractMode::Padded, ExtractMode::Trimmed, ExtractMode::Raw] {
+                firmware.file_slice(file, mode);
+            }
+        }
+        lint(&firmware);
+    }
+});
@@ -0,0 +1,128 @@
+#![no_main]
+
+//! Structure-aware fuzzing: BLOBs are synthesized from an arbitrary description
+//! and then have their lengths, tags and trailers perturbed, which reaches much
+//! deeper into the parser than random bytes.
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use ufwt::file::{ExtractMode, UnidenFirmware};
+use ufwt::format::{GpsDbCountry, RDModel, HEADER_SECTIONS, SECTION_TAGS};
+use ufwt::lint::lint;
+use ufwt::synth::{BlobSpec, ComponentSpec, Mutation};
+
+#[derive(Arbitrary, Debug)]
+struct Component {
+    kind: u8,
+    length: u16,
+    version: u16,
+    model: u8,
+    country: u8,
+    poi: u8,
+}
+
+#[derive(Arbitrary, Debug)]
+enum FuzzMutation {
+    Length { component: u8, length: i32 },
+    Tag { component: u8, tag: [u8; 4] },
+    Trailer { component: u8, index: u8, value: u8 },
+    SoundDbFlag(bool),
+    Truncate(u16),
+    Insert { offset: u16, bytes: Vec<u8> },
+    Byte { offset: u16, value: u8 },
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    model: u8,
+    package: bool,
+    format_version: Option<i32>,
+    components: Vec<Component>,
+    mutations: Vec<FuzzMutation>,
+}
+
+impl From<&FuzzMutation> for Mutation {
+    fn from(mutation: &FuzzMutation) -> Mutation {
+        match *mutation {
+            FuzzMutation::Length { component, length } => Mutation::Length {
+                component: component as usize,
+                length,
+            },
+            FuzzMutation::Tag { component, tag } => Mutation::Tag {
+                component: component as usize,
+                tag,
+            },
+            FuzzMutation::Trailer {
+                component,
+                index,
+                value,
+            } => Mutation::Trailer {
+                component: component as usize,
+                index: index as usize,
+                value,
+            },
+            FuzzMutation::SoundDbFlag(set) => Mutation::SoundDbFlag(set),
+            FuzzMutation::Truncate(length) => Mutation::Truncate(length as usize),
+            FuzzMutation::Insert { offset, ref bytes } => Mutation::Insert {
+                offset: offset as usize,
+                bytes: bytes.clone(),
+            },
+            FuzzMutation::Byte { offset, value } => Mutation::Byte {
+                offset: offset as usize,
+                value,
+            },
+        }
+    }
+}
+
+fn spec(input: &Input) -> BlobSpec {
+    let names: Vec<&'static str> = HEADER_SECTIONS
+        .iter()
+        .map(|(name, _)| *name)
+        .chain(SECTION_TAGS.iter().map(|(_, name, _)| *name))
+        .collect();
+    let countries = [
+        GpsDbCountry::Us,
+        GpsDbCountry::Nz,
+        GpsDbCountry::Il,
+        GpsDbCountry::Eu,
+    ];
+
+    let model = RDModel::from(input.model);
+    let mut spec = match (input.package, input.format_version) {
+        (true, _) => BlobSpec::package(),
+        (false, Some(format_version)) => BlobSpec::merged(model, format_version),
+        (false, None) => BlobSpec::legacy(model),
+    };
+    for component in input.components.iter().take(24) {
+        let name = names[component.kind as usize % names.len()];
+        let country = countries[component.country as usize % countries.len()];
+        spec = spec.with(
+            ComponentSpec::new(name, component.length as usize, component.version as i32)
+                .model(RDModel::from(component.model))
+                .gps_db(country, component.poi as i32),
+        );
+    }
+    spec
+}
+
+fuzz_target!(|input: Input| {
+    let mut blob = spec(&input).build();
+    for mutation in input.mutations.iter().take(8) {
+        blob.mutate(&mutation.into());
+    }
+
+    for lenient in [false, true] {
+        let mut firmware = UnidenFirmware::from_buffer(blob.data.clone());
+        firmware.lenient = lenient;
+        if firmware.read_buffer().is_err() {
+            continue;
+        }
+        for file in firmware.files() {
+            for mode in [ExtractMode::Padded, ExtractMode::Trimmed, ExtractMode::Raw] {
+                firmware.file_slice(file, mode);
+            }
+        }
+        lint(&firmware);
+    }
+});
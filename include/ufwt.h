@@ -0,0 +1,112 @@
+#ifndef UFWT_H
+#define UFWT_H
+
+/* Generated by cbindgen from src/capi.rs, build with `--features header` to update. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * A parsed BLOB, created by `ufwt_open` and released by `ufwt_free`.
+ */
+typedef struct UfwtFirmware UfwtFirmware;
+
+/**
+ * Description of a component, filled in by `ufwt_component`.
+ */
+typedef struct UfwtComponent {
+  /**
+   * Component kind, e.g. `dsp_nu`
+   */
+  const char *kind;
+  /**
+   * Section tag, e.g. `STUI`, NULL for the sections described by the header
+   */
+  const char *tag;
+  /**
+   * Offset of the payload in the BLOB
+   */
+  uint64_t offset;
+  /**
+   * Payload length including its padding
+   */
+  uint64_t length;
+  /**
+   * Payload length declared in the header or section tag
+   */
+  uint64_t declared_length;
+  /**
+   * Version held by the trailer
+   */
+  int32_t version;
+  /**
+   * Whether the component was recovered by lenient parsing and may be incomplete
+   */
+  bool damaged;
+} UfwtComponent;
+
+/**
+ * Parse the `length` bytes at `data`, which are copied and can be released
+ * once this returns. Returns NULL if the BLOB can't be parsed, see
+ * `ufwt_last_error`. In `lenient` mode, damaged sections are skipped over
+ * instead.
+ *
+ * # Safety
+ *
+ * `data` must point to `length` readable bytes.
+ */
+struct UfwtFirmware *ufwt_open(const uint8_t *data, size_t length, bool lenient);
+
+/**
+ * Message of the error of the last `ufwt_open` on this thread, or of a
+ * panic caught since, NULL if it succeeded and nothing panicked. Valid until
+ * the next `ufwt_open` on the same thread.
+ */
+const char *ufwt_last_error(void);
+
+/**
+ * Number of components found in the BLOB, 0 if `firmware` is NULL.
+ *
+ * # Safety
+ *
+ * `firmware` must be NULL or returned by `ufwt_open` and not freed yet.
+ */
+size_t ufwt_component_count(const struct UfwtFirmware *firmware);
+
+/**
+ * Describe component `index` in `out`. Returns false, leaving `out`
+ * untouched, if there is no such component. The strings in `out` are valid
+ * until `firmware` is freed.
+ *
+ * # Safety
+ *
+ * `firmware` must be NULL or returned by `ufwt_open` and not freed yet, and
+ * `out` must be NULL or point to a writable `UfwtComponent`.
+ */
+bool ufwt_component(const struct UfwtFirmware *firmware, size_t index, struct UfwtComponent *out);
+
+/**
+ * Bytes of component `index`, its declared length without padding, as
+ * written by `extract --trim`. The length is stored in `length`. Returns NULL
+ * if there is no such component. Valid until `firmware` is freed.
+ *
+ * # Safety
+ *
+ * `firmware` must be NULL or returned by `ufwt_open` and not freed yet, and
+ * `length` must be NULL or point to a writable `size_t`.
+ */
+const uint8_t *ufwt_component_data(const struct UfwtFirmware *firmware,
+                                   size_t index,
+                                   size_t *length);
+
+/**
+ * Release a BLOB returned by `ufwt_open`. Does nothing if `firmware` is NULL.
+ *
+ * # Safety
+ *
+ * `firmware` must be NULL or returned by `ufwt_open` and not freed yet.
+ */
+void ufwt_free(struct UfwtFirmware *firmware);
+
+#endif /* UFWT_H */
@@ -0,0 +1,264 @@
+use crate::edit::check_rd_version;
+use crate::file::{db_terminator, FWFileKind, FileInfo, FileInfoBase, UnidenFirmware};
+use crate::format::{
+    decode_old_model, encode_old_model, padding_block, terminator_for_component, trailer_length,
+    GpsDbCountry, GpsDbType, RDModel, HEADER_SECTIONS, SECTION_TAGS, SOUND_DB_KEY,
+};
+use crate::gpsdb::{GpsDb, Poi};
+use std::io;
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+enum Source {
+    /// Payload and trailer version, the payload as written by `extract --trim`
+    Bytes(Vec<u8>, i32),
+    GpsDb(GpsDb, GpsDbType),
+}
+
+/// Composes a merged firmware BLOB from component payloads.
+///
+/// The header with its sound DB flag, padding, section tags, trailers and the
+/// closing `NMGF` record are all computed by [`FirmwareBuilder::build`].
+/// Components described by the header are placed first, the others follow as
+/// tagged sections in the order they were added.
+pub struct FirmwareBuilder {
+    model: RDModel,
+    format_version: i32,
+    components: Vec<(&'static str, Source)>,
+}
+
+impl Default for FirmwareBuilder {
+    fn default() -> Self {
+        FirmwareBuilder::new()
+    }
+}
+
+impl FirmwareBuilder {
+    pub fn new() -> FirmwareBuilder {
+        FirmwareBuilder {
+            model: RDModel::Unknown,
+            format_version: 1,
+            components: vec![],
+        }
+    }
+
+    /// Model written to the trailer of every padded component.
+    pub fn model(mut self, model: RDModel) -> FirmwareBuilder {
+        self.model = model;
+        self
+    }
+
+    /// Format version held by the `NMGF` record, 1 by default.
+    pub fn format_version(mut self, format_version: i32) -> FirmwareBuilder {
+        self.format_version = format_version;
+        self
+    }
+
+    /// Add a component, e.g. `FWFileKind::DspStm`. Sound DBs include their
+    /// 12-byte trailer block, whose version is overwritten with `version`.
+    pub fn component(
+        mut self,
+        kind: fn(FileInfo) -> FWFileKind,
+        data: &[u8],
+        version: i32,
+    ) -> FirmwareBuilder {
+        let name = kind(FileInfo::Base(FileInfoBase::default())).to_name();
+        self.components
+            .push((name, Source::Bytes(data.to_vec(), version)));
+        self
+    }
+
+    /// Add a GPS DB holding `pois`: `gps_db` the first time, `gps_db_second` after.
+    /// Only old encoding GPS DBs can be built.
+    pub fn gps_db(
+        mut self,
+        country: GpsDbCountry,
+        file_type: GpsDbType,
+        pois: Vec<Poi>,
+        version: i32,
+    ) -> FirmwareBuilder {
+        let name = if self.components.iter().any(|(name, _)| *name == "gps_db") {
+            "gps_db_second"
+        } else {
+            "gps_db"
+        };
+        let gps_db = GpsDb {
+            country,
+            version,
+            pois,
+        };
+        self.components
+            .push((name, Source::GpsDb(gps_db, file_type)));
+        self
+    }
+
+    /// Model bits of the trailer word of padded components.
+    fn model_bits(&self) -> io::Result<i16> {
+        let model = self.model as u8;
+        if model > 0x3F {
+            return Err(invalid_input(format!(
+                "Model {} does not fit in the trailer's model bits",
+                self.model.to_name()
+            )));
+        }
+        Ok((model as i16) << 10)
+    }
+
+    /// Payload of component `name` followed by its trailer and terminator, and
+    /// the length to declare for it.
+    fn section(&self, name: &str, source: &Source) -> io::Result<(usize, Vec<u8>)> {
+        let terminator = terminator_for_component(name).unwrap();
+        let (data, version) = match source {
+            Source::Bytes(data, version) => (data.clone(), *version),
+            Source::GpsDb(gps_db, GpsDbType::GpsDbOldEnc) => (gps_db.build()?, gps_db.version),
+            Source::GpsDb(_, _) => {
+                return Err(invalid_input(
+                    "Only old encoding GPS DBs can be built".into(),
+                ))
+            }
+        };
+
+        let mut out = data;
+        let declared = out.len();
+        match db_terminator(name) {
+            Some((skip, _)) => {
+                if declared < 12 {
+                    return Err(invalid_input(format!(
+                        "{} must include its 12-byte trailer block",
+                        name
+                    )));
+                }
+                if name.starts_with("sound_db") {
+                    let version = check_rd_version(version as i64)?;
+                    let block = declared - 12;
+                    let word = decode_old_model(SOUND_DB_KEY, &out, block, 4);
+                    let word = i32::from_le_bytes(word.try_into().unwrap());
+                    let word = (word & !0x3FF) | version as i32;
+                    let encoded = encode_old_model(SOUND_DB_KEY, &word.to_le_bytes(), 0, 4);
+                    out[block..block + 4].copy_from_slice(&encoded);
+                }
+                out.resize(declared + skip, 0);
+            }
+            None => {
+                let version = check_rd_version(version as i64)?;
+                let block = padding_block(name).unwrap() as usize;
+                out.resize((declared / block + 1) * block, 0xFF);
+                out.extend_from_slice(&(self.model_bits()? | version).to_le_bytes());
+            }
+        }
+        out.extend_from_slice(terminator.as_bytes());
+        Ok((declared, out))
+    }
+
+    /// Lay out the BLOB and check that it parses back into the components added.
+    pub fn build(&self) -> io::Result<Vec<u8>> {
+        let mut sections = vec![];
+        for (i, (name, source)) in self.components.iter().enumerate() {
+            if self.components[..i].iter().any(|(n, _)| n == name) {
+                return Err(invalid_input(format!("{} was added twice", name)));
+            }
+            let (declared, section) = self.section(name, source)?;
+            let declared = i32::try_from(declared)
+                .map_err(|_| invalid_input(format!("{} is too large", name)))?;
+            sections.push((*name, declared as u32, section));
+        }
+        let ui_nu = sections.iter().find(|(name, _, _)| *name == "ui_nu");
+        if ui_nu.is_some_and(|(_, declared, _)| *declared > 0xFFFFFF) {
+            return Err(invalid_input(format!(
+                "ui_nu is limited to {} bytes",
+                0xFFFFFF
+            )));
+        }
+
+        let (out, offsets) = lay_out(&sections, true, Some(self.format_version));
+
+        // the components, where they were put and their lengths, in BLOB order
+        let mut expected = sections
+            .iter()
+            .zip(offsets)
+            .map(|((name, declared, section), offset)| {
+                let length = section.len() - trailer_length(name);
+                (offset.unwrap(), *name, *declared as i32, length as i32)
+            })
+            .collect::<Vec<_>>();
+        expected.sort();
+
+        let mut firmware = UnidenFirmware::from_bytes(&out);
+        firmware.read_buffer()?;
+        let found = firmware
+            .files()
+            .iter()
+            .map(|file| {
+                let info = file.info().base();
+                let (offset, name) = (info.offset() as usize, file.kind().to_name());
+                (offset, name, info.declared_length(), info.length())
+            })
+            .collect::<Vec<_>>();
+        if found != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Built BLOB does not parse back into its components",
+            ));
+        }
+        Ok(out)
+    }
+}
+
+/// Lay out BLOB sections the way the firmware updater expects them.
+///
+/// `sections` holds the name, declared length and payload of each component,
+/// the payload being followed by its trailer and terminator. With `header`,
+/// the header and the sections it describes come first, otherwise those
+/// sections are left out. The others follow behind their tag in the given
+/// order, then the `NMGF` record if there is a `format_version`.
+///
+/// Returns the BLOB and the start of each payload, in the order of `sections`.
+pub(crate) fn lay_out(
+    sections: &[(&'static str, u32, Vec<u8>)],
+    header: bool,
+    format_version: Option<i32>,
+) -> (Vec<u8>, Vec<Option<usize>>) {
+    let find = |name: &str| sections.iter().position(|(n, _, _)| *n == name);
+    let mut offsets = vec![None; sections.len()];
+    let mut out = vec![];
+
+    if header {
+        let declared = |name: &str| find(name).map_or(0, |i| sections[i].1);
+        let includes_sound_db = find("sound_dbnu").is_some();
+        out.extend_from_slice(
+            &(declared("ui_nu") | (includes_sound_db as u32) << 24).to_le_bytes(),
+        );
+        out.extend_from_slice(&declared("dsp_nu").to_le_bytes());
+        out.extend_from_slice(&declared("gps_nu").to_le_bytes());
+        if includes_sound_db {
+            out.extend_from_slice(&[0; 8]);
+            out.extend_from_slice(&declared("sound_dbnu").to_le_bytes());
+        }
+        for (name, _) in HEADER_SECTIONS {
+            if let Some(i) = find(name) {
+                offsets[i] = Some(out.len());
+                out.extend_from_slice(&sections[i].2);
+            }
+        }
+    }
+
+    for (i, (name, declared, section)) in sections.iter().enumerate() {
+        let Some((tag, _, _)) = SECTION_TAGS.iter().find(|(_, n, _)| n == name) else {
+            continue;
+        };
+        out.extend_from_slice(tag.as_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&declared.to_le_bytes());
+        offsets[i] = Some(out.len());
+        out.extend_from_slice(section);
+    }
+
+    if let Some(format_version) = format_version {
+        out.extend_from_slice(b"NMGF");
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&format_version.to_le_bytes());
+    }
+    (out, offsets)
+}
@@ -0,0 +1,206 @@
+//! C API of the `cdylib`, declared in `include/ufwt.h`.
+//!
+//! The header is regenerated by building with the `header` feature.
+
+use crate::file::{ExtractMode, UnidenFirmware};
+use crate::view::ComponentView;
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::{ptr, slice};
+
+/// A parsed BLOB, created by `ufwt_open` and released by `ufwt_free`.
+pub struct UfwtFirmware {
+    firmware: UnidenFirmware,
+    /// NUL-terminated kind and tag of each component
+    names: Vec<(CString, Option<CString>)>,
+}
+
+/// Description of a component, filled in by `ufwt_component`.
+#[repr(C)]
+pub struct UfwtComponent {
+    /// Component kind, e.g. `dsp_nu`
+    pub kind: *const c_char,
+    /// Section tag, e.g. `STUI`, NULL for the sections described by the header
+    pub tag: *const c_char,
+    /// Offset of the payload in the BLOB
+    pub offset: u64,
+    /// Payload length including its padding
+    pub length: u64,
+    /// Payload length declared in the header or section tag
+    pub declared_length: u64,
+    /// Version held by the trailer
+    pub version: i32,
+    /// Whether the component was recovered by lenient parsing and may be incomplete
+    pub damaged: bool,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', "")).unwrap();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// Run `f`, returning `failed` with the panic message as the last error if it
+/// panics, as unwinding into C is undefined behaviour.
+fn catch_panic<T>(failed: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".into());
+        set_last_error(format!("Panic in ufwt: {}", message));
+        failed
+    })
+}
+
+/// Component `index` of `firmware`, borrowed for as long as the caller says.
+unsafe fn component<'a>(
+    firmware: *const UfwtFirmware,
+    index: usize,
+) -> Option<(&'a UfwtFirmware, ComponentView<'a>)> {
+    let firmware = firmware.as_ref()?;
+    Some((firmware, firmware.firmware.components().nth(index)?))
+}
+
+/// Parse the `length` bytes at `data`, which are copied and can be released
+/// once this returns. Returns NULL if the BLOB can't be parsed, see
+/// `ufwt_last_error`. In `lenient` mode, damaged sections are skipped over
+/// instead.
+///
+/// # Safety
+///
+/// `data` must point to `length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ufwt_open(
+    data: *const u8,
+    length: usize,
+    lenient: bool,
+) -> *mut UfwtFirmware {
+    catch_panic(ptr::null_mut(), || {
+        let data = if length == 0 {
+            &[][..]
+        } else if data.is_null() {
+            set_last_error("data is NULL".into());
+            return ptr::null_mut();
+        } else {
+            slice::from_raw_parts(data, length)
+        };
+
+        let mut firmware = UnidenFirmware::from_bytes(data);
+        firmware.lenient = lenient;
+        if let Err(e) = firmware.read_buffer() {
+            set_last_error(e.to_string());
+            return ptr::null_mut();
+        }
+
+        let names = firmware
+            .components()
+            .map(|component| {
+                let kind = CString::new(component.kind().to_name()).unwrap();
+                let tag = component.tag().map(|tag| CString::new(tag).unwrap());
+                (kind, tag)
+            })
+            .collect();
+        LAST_ERROR.with(|error| *error.borrow_mut() = None);
+        Box::into_raw(Box::new(UfwtFirmware { firmware, names }))
+    })
+}
+
+/// Message of the error of the last `ufwt_open` on this thread, or of a
+/// panic caught since, NULL if it succeeded and nothing panicked. Valid until
+/// the next `ufwt_open` on the same thread.
+#[no_mangle]
+pub extern "C" fn ufwt_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Number of components found in the BLOB, 0 if `firmware` is NULL.
+///
+/// # Safety
+///
+/// `firmware` must be NULL or returned by `ufwt_open` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ufwt_component_count(firmware: *const UfwtFirmware) -> usize {
+    firmware.as_ref().map_or(0, |firmware| firmware.names.len())
+}
+
+/// Describe component `index` in `out`. Returns false, leaving `out`
+/// untouched, if there is no such component. The strings in `out` are valid
+/// until `firmware` is freed.
+///
+/// # Safety
+///
+/// `firmware` must be NULL or returned by `ufwt_open` and not freed yet, and
+/// `out` must be NULL or point to a writable `UfwtComponent`.
+#[no_mangle]
+pub unsafe extern "C" fn ufwt_component(
+    firmware: *const UfwtFirmware,
+    index: usize,
+    out: *mut UfwtComponent,
+) -> bool {
+    catch_panic(false, || {
+        let (Some((firmware, view)), Some(out)) = (component(firmware, index), out.as_mut()) else {
+            return false;
+        };
+        let (kind, tag) = &firmware.names[index];
+        let info = view.file().info().base();
+        *out = UfwtComponent {
+            kind: kind.as_ptr(),
+            tag: tag.as_ref().map_or(ptr::null(), |tag| tag.as_ptr()),
+            offset: info.offset() as u64,
+            length: info.length() as u64,
+            declared_length: info.declared_length().max(0) as u64,
+            version: view.version(),
+            damaged: view.file().damaged(),
+        };
+        true
+    })
+}
+
+/// Bytes of component `index`, its declared length without padding, as
+/// written by `extract --trim`. The length is stored in `length`. Returns NULL
+/// if there is no such component. Valid until `firmware` is freed.
+///
+/// # Safety
+///
+/// `firmware` must be NULL or returned by `ufwt_open` and not freed yet, and
+/// `length` must be NULL or point to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn ufwt_component_data(
+    firmware: *const UfwtFirmware,
+    index: usize,
+    length: *mut usize,
+) -> *const u8 {
+    catch_panic(ptr::null(), || {
+        let Some((_, view)) = component(firmware, index) else {
+            return ptr::null();
+        };
+        let data = view.slice(ExtractMode::Trimmed);
+        if let Some(length) = length.as_mut() {
+            *length = data.len();
+        }
+        data.as_ptr()
+    })
+}
+
+/// Release a BLOB returned by `ufwt_open`. Does nothing if `firmware` is NULL.
+///
+/// # Safety
+///
+/// `firmware` must be NULL or returned by `ufwt_open` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ufwt_free(firmware: *mut UfwtFirmware) {
+    if !firmware.is_null() {
+        drop(Box::from_raw(firmware));
+    }
+}
@@ -0,0 +1,337 @@
+use crate::file::{db_terminator, read_gps_db_info, FileInfoBase};
+use crate::format::{
+    component_for_terminator, padding_block, RDModel, HEADER_SECTIONS, SECTION_TAGS,
+};
+use crate::gpsdb::RECORD_LEN;
+use crate::trailer::Trailer;
+use std::path::Path;
+use std::{fs, io};
+
+/// How the boundaries of a carved section were found, from most to least reliable.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CarveMethod {
+    /// A section tag whose length leads exactly to the terminator
+    Tag,
+    /// A matching section tag, but its length doesn't agree with the terminator
+    TagLengthMismatch,
+    /// The POI count in the GPS DB trailer
+    PoiCount,
+    /// Padding blocks counted back towards the end of the previous section
+    Aligned,
+    /// Right after the legacy header section it always follows
+    SectionOrder,
+    /// Everything since the end of the previous section
+    Fallback,
+    /// A section tag without a terminator, e.g. at the end of a partial download
+    Unterminated,
+}
+
+impl CarveMethod {
+    fn confidence(&self) -> f32 {
+        match self {
+            CarveMethod::Tag => 0.95,
+            CarveMethod::Aligned | CarveMethod::SectionOrder => 0.7,
+            CarveMethod::TagLengthMismatch | CarveMethod::PoiCount => 0.6,
+            CarveMethod::Unterminated => 0.4,
+            CarveMethod::Fallback => 0.3,
+        }
+    }
+
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            CarveMethod::Tag => "tag",
+            CarveMethod::TagLengthMismatch => "tag with mismatched length",
+            CarveMethod::PoiCount => "POI count",
+            CarveMethod::Aligned => "padding",
+            CarveMethod::SectionOrder => "section order",
+            CarveMethod::Fallback => "previous section",
+            CarveMethod::Unterminated => "tag without terminator",
+        }
+    }
+}
+
+/// A section found in an arbitrary dump.
+pub struct CarvedSection {
+    /// Component name, as in [`crate::file::FWFileKind::to_name`]
+    pub name: &'static str,
+    /// Start of the payload
+    pub start: usize,
+    /// End of the payload (padding included)
+    pub end: usize,
+    /// End of the terminator, or of the payload if there is none
+    pub trailer_end: usize,
+    pub version: Option<i32>,
+    pub model: Option<RDModel>,
+    pub method: CarveMethod,
+    /// Between 0 and 1
+    pub confidence: f32,
+}
+
+impl CarvedSection {
+    pub fn file_name(&self) -> String {
+        format!("{:08x}_{}.bin", self.start, self.name)
+    }
+}
+
+struct TagHeader {
+    pos: usize,
+    name: &'static str,
+    length: usize,
+}
+
+impl TagHeader {
+    fn start(&self) -> usize {
+        self.pos + 12
+    }
+
+    /// End of the payload according to the declared length.
+    fn end(&self) -> usize {
+        match padding_block(self.name) {
+            Some(block) => {
+                let block = block as usize;
+                self.start() + (self.length / block + 1) * block
+            }
+            None => self.start() + self.length,
+        }
+    }
+}
+
+fn find_tag_headers(data: &[u8]) -> Vec<TagHeader> {
+    let mut headers = vec![];
+    for pos in 0..data.len().saturating_sub(11) {
+        let Some((_, name, _)) = SECTION_TAGS
+            .iter()
+            .find(|(tag, _, _)| data[pos..pos + 4] == *tag.as_bytes())
+        else {
+            continue;
+        };
+        let length = u32::from_le_bytes(data[pos + 8..pos + 12].try_into().unwrap()) as usize;
+        headers.push(TagHeader { pos, name, length });
+    }
+    headers
+}
+
+/// Scan `data` for `DRSWxxx` terminators and section tags and rebuild the
+/// boundaries of the sections they belong to, using the tags where they
+/// survived and the padding rules otherwise.
+pub fn carve(data: &[u8]) -> Vec<CarvedSection> {
+    let headers = find_tag_headers(data);
+    let mut used = vec![false; headers.len()];
+
+    let mut sections = vec![];
+    let mut previous_end = 0;
+    for pos in 0..data.len().saturating_sub(6) {
+        if &data[pos..pos + 4] != b"DRSW" {
+            continue;
+        }
+        let Some(name) = component_for_terminator(&data[pos..pos + 7]) else {
+            continue;
+        };
+
+        // the closest tag for this component since the previous section
+        let candidates = headers
+            .iter()
+            .enumerate()
+            .filter(|(_, header)| header.name == name && header.pos >= previous_end)
+            .filter(|(_, header)| header.start() <= pos);
+        let (section, header) = match db_terminator(name) {
+            Some((skip, _)) => {
+                // sound_dbnu directly follows the other sections described by the header
+                let follows_header = sections.last().is_some_and(|s: &CarvedSection| {
+                    HEADER_SECTIONS.iter().any(|(name, _)| *name == s.name)
+                });
+                let previous = (previous_end, follows_header);
+                carve_db(data, pos, skip, name, previous, candidates)
+            }
+            None => carve_padded(data, pos, name, previous_end, candidates),
+        };
+        let Some(section) = section else {
+            continue;
+        };
+        if let Some(i) = header {
+            used[i] = true;
+        }
+        previous_end = section.trailer_end;
+        sections.push(section);
+    }
+
+    // tags whose terminator is gone, e.g. cut off at the end of a dump
+    for (header, _) in headers.iter().zip(used).filter(|(_, used)| !used) {
+        if header.start() >= data.len()
+            || sections
+                .iter()
+                .any(|s| s.start <= header.pos && header.pos < s.trailer_end)
+        {
+            continue;
+        }
+        let end = header.end().min(data.len());
+        let mut confidence = CarveMethod::Unterminated.confidence();
+        if end < header.end() {
+            // truncated
+            confidence /= 2.0;
+        }
+        sections.push(CarvedSection {
+            name: header.name,
+            start: header.start(),
+            end,
+            trailer_end: end,
+            version: None,
+            model: None,
+            method: CarveMethod::Unterminated,
+            confidence,
+        });
+    }
+    sections.sort_by_key(|section| section.start);
+    sections
+}
+
+/// Sound and GPS DBs, stored unpadded and ending with a 12-byte trailer block.
+fn carve_db<'a>(
+    data: &[u8],
+    terminator: usize,
+    skip: usize,
+    name: &'static str,
+    (previous_end, follows_header): (usize, bool),
+    mut candidates: impl DoubleEndedIterator<Item = (usize, &'a TagHeader)>,
+) -> (Option<CarvedSection>, Option<usize>) {
+    let Some(end) = terminator.checked_sub(skip) else {
+        return (None, None);
+    };
+    let Some(trailer) = Trailer::read(data, name, end) else {
+        return (None, None);
+    };
+
+    let poi = if name.starts_with("gps_db") {
+        let base = FileInfoBase {
+            length: 0,
+            declared_length: 0,
+            offset: 0,
+            version: 0,
+        };
+        match read_gps_db_info(&trailer.raw()[..12], base) {
+            Some(info) => Some(info.poi),
+            None => return (None, None),
+        }
+    } else {
+        None
+    };
+
+    // the payload can't start within its own trailer block
+    let last_start = end.saturating_sub(12);
+    let tagged = candidates.next_back().map(|(i, header)| {
+        let method = if header.end() == end {
+            CarveMethod::Tag
+        } else {
+            CarveMethod::TagLengthMismatch
+        };
+        (header.start(), method, Some(i))
+    });
+    let tagged = tagged.filter(|(start, _, _)| *start <= last_start);
+    let by_poi_count = poi
+        .and_then(|poi| usize::try_from(poi).ok())
+        .and_then(|poi| last_start.checked_sub(poi.checked_mul(RECORD_LEN)?))
+        .filter(|start| *start >= previous_end)
+        .map(|start| (start, CarveMethod::PoiCount, None));
+    let by_order = (name == "sound_dbnu" && follows_header).then_some((
+        previous_end,
+        CarveMethod::SectionOrder,
+        None,
+    ));
+    let fallback = (previous_end, CarveMethod::Fallback, None);
+    let (start, method, header) = match tagged {
+        Some((_, CarveMethod::TagLengthMismatch, _)) if by_poi_count.is_some() => {
+            by_poi_count.unwrap()
+        }
+        _ => tagged.or(by_poi_count).or(by_order).unwrap_or(fallback),
+    };
+    if start > last_start {
+        return (None, None);
+    }
+
+    let section = CarvedSection {
+        name,
+        start,
+        end,
+        trailer_end: terminator + 7,
+        version: Some(trailer.version()),
+        model: None,
+        method,
+        confidence: method.confidence(),
+    };
+    (Some(section), header)
+}
+
+/// Sections padded to 512 (or 1024) bytes and followed by a model/version word.
+fn carve_padded<'a>(
+    data: &[u8],
+    terminator: usize,
+    name: &'static str,
+    previous_end: usize,
+    mut candidates: impl DoubleEndedIterator<Item = (usize, &'a TagHeader)>,
+) -> (Option<CarvedSection>, Option<usize>) {
+    let Some(end) = terminator.checked_sub(2).filter(|end| *end >= previous_end) else {
+        return (None, None);
+    };
+    let Some(trailer) = Trailer::read(data, name, end) else {
+        return (None, None);
+    };
+    let block = padding_block(name).unwrap() as usize;
+
+    let (start, method, header) = match candidates.next_back() {
+        Some((i, header)) if header.end() == end => (header.start(), CarveMethod::Tag, Some(i)),
+        Some((i, header)) => (header.start(), CarveMethod::TagLengthMismatch, Some(i)),
+        None => {
+            let blocks = (end - previous_end) / block;
+            if blocks == 0 {
+                (previous_end, CarveMethod::Fallback, None)
+            } else {
+                (end - blocks * block, CarveMethod::Aligned, None)
+            }
+        }
+    };
+
+    let mut confidence = method.confidence();
+    if method == CarveMethod::Aligned {
+        // bytes left over before the section, other than a BLOB header
+        let leftover = start - previous_end;
+        if leftover != 0 && !(previous_end == 0 && (leftover == 12 || leftover == 24)) {
+            confidence -= 0.2;
+        }
+    }
+    let model = trailer.model().unwrap();
+    if model == RDModel::Unknown {
+        confidence -= 0.1;
+    } else {
+        confidence += 0.05;
+    }
+
+    let section = CarvedSection {
+        name,
+        start,
+        end,
+        trailer_end: terminator + 7,
+        version: Some(trailer.version()),
+        model: Some(model),
+        method,
+        confidence: confidence.clamp(0.0, 1.0),
+    };
+    (Some(section), header)
+}
+
+/// Write the payload of every carved section to `directory`.
+pub fn write_sections(data: &[u8], sections: &[CarvedSection], directory: &Path) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+    for section in sections {
+        let payload = data.get(section.start..section.end).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid bounds for {}: {:#x}-{:#x}",
+                    section.name, section.start, section.end
+                ),
+            )
+        })?;
+        fs::write(directory.join(section.file_name()), payload)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,522 @@
+use crate::file::{db_terminator, ExtractMode, UnidenFirmware};
+use crate::view::ComponentView;
+use std::collections::BTreeMap;
+use ufwt_core::section::Header;
+
+/// Checksum algorithms tried by [`find_checksums`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Algorithm {
+    Crc16CcittFalse,
+    Crc16Xmodem,
+    Crc16Arc,
+    Crc16Modbus,
+    Crc16Kermit,
+    Crc32,
+    Crc32Mpeg2,
+    Crc32Bzip2,
+    /// Sum of the bytes
+    Sum8,
+    Sum16,
+    Sum32,
+    /// Sum of the little endian 16-bit words
+    Sum16Words,
+    /// Sum of the little endian 32-bit words
+    Sum32Words,
+    Xor8,
+    /// XOR of the little endian 32-bit words
+    Xor32Words,
+    Fletcher16,
+    /// Fletcher-32 over little endian 16-bit words
+    Fletcher32,
+}
+
+/// Parameters of a CRC as in the usual catalogues, with its lookup table.
+struct Crc {
+    width: u32,
+    init: u32,
+    reflected: bool,
+    xor_out: u32,
+    table: [u32; 256],
+}
+
+impl Crc {
+    const fn new(width: u32, poly: u32, init: u32, reflected: bool, xor_out: u32) -> Crc {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            if reflected {
+                let poly = poly.reverse_bits() >> (32 - width);
+                while bit < 8 {
+                    crc = if crc & 1 != 0 {
+                        (crc >> 1) ^ poly
+                    } else {
+                        crc >> 1
+                    };
+                    bit += 1;
+                }
+            } else {
+                // Work on the top bits of a 32-bit register whatever the width
+                let poly = poly << (32 - width);
+                crc <<= 24;
+                while bit < 8 {
+                    crc = if crc & 0x8000_0000 != 0 {
+                        (crc << 1) ^ poly
+                    } else {
+                        crc << 1
+                    };
+                    bit += 1;
+                }
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        Crc {
+            width,
+            init,
+            reflected,
+            xor_out,
+            table,
+        }
+    }
+
+    fn compute(&self, data: &[u8]) -> u32 {
+        let mask = u32::MAX >> (32 - self.width);
+        let crc = if self.reflected {
+            data.iter().fold(self.init, |crc, &b| {
+                self.table[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8)
+            })
+        } else {
+            let shift = 32 - self.width;
+            let crc = data.iter().fold(self.init << shift, |crc, &b| {
+                self.table[((crc >> 24) ^ b as u32) as usize] ^ (crc << 8)
+            });
+            crc >> shift
+        };
+        (crc ^ self.xor_out) & mask
+    }
+}
+
+static CRC16_CCITT_FALSE: Crc = Crc::new(16, 0x1021, 0xFFFF, false, 0);
+static CRC16_XMODEM: Crc = Crc::new(16, 0x1021, 0, false, 0);
+static CRC16_ARC: Crc = Crc::new(16, 0x8005, 0, true, 0);
+static CRC16_MODBUS: Crc = Crc::new(16, 0x8005, 0xFFFF, true, 0);
+static CRC16_KERMIT: Crc = Crc::new(16, 0x1021, 0, true, 0);
+static CRC32_MPEG2: Crc = Crc::new(32, 0x04C11DB7, 0xFFFFFFFF, false, 0);
+static CRC32_BZIP2: Crc = Crc::new(32, 0x04C11DB7, 0xFFFFFFFF, false, 0xFFFFFFFF);
+
+/// Little endian words of `data`, the last one padded with zeroes.
+fn words<const N: usize>(data: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    data.chunks(N).map(|chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        u32::from_le_bytes(word)
+    })
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 17] = [
+        Algorithm::Crc16CcittFalse,
+        Algorithm::Crc16Xmodem,
+        Algorithm::Crc16Arc,
+        Algorithm::Crc16Modbus,
+        Algorithm::Crc16Kermit,
+        Algorithm::Crc32,
+        Algorithm::Crc32Mpeg2,
+        Algorithm::Crc32Bzip2,
+        Algorithm::Sum8,
+        Algorithm::Sum16,
+        Algorithm::Sum32,
+        Algorithm::Sum16Words,
+        Algorithm::Sum32Words,
+        Algorithm::Xor8,
+        Algorithm::Xor32Words,
+        Algorithm::Fletcher16,
+        Algorithm::Fletcher32,
+    ];
+
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            Algorithm::Crc16CcittFalse => "crc16/ccitt-false",
+            Algorithm::Crc16Xmodem => "crc16/xmodem",
+            Algorithm::Crc16Arc => "crc16/arc",
+            Algorithm::Crc16Modbus => "crc16/modbus",
+            Algorithm::Crc16Kermit => "crc16/kermit",
+            Algorithm::Crc32 => "crc32",
+            Algorithm::Crc32Mpeg2 => "crc32/mpeg-2",
+            Algorithm::Crc32Bzip2 => "crc32/bzip2",
+            Algorithm::Sum8 => "sum8",
+            Algorithm::Sum16 => "sum16",
+            Algorithm::Sum32 => "sum32",
+            Algorithm::Sum16Words => "sum16/words",
+            Algorithm::Sum32Words => "sum32/words",
+            Algorithm::Xor8 => "xor8",
+            Algorithm::Xor32Words => "xor32/words",
+            Algorithm::Fletcher16 => "fletcher16",
+            Algorithm::Fletcher32 => "fletcher32",
+        }
+    }
+
+    /// Size of the checksum in bytes.
+    pub fn width(&self) -> usize {
+        match self {
+            Algorithm::Sum8 | Algorithm::Xor8 => 1,
+            Algorithm::Crc16CcittFalse
+            | Algorithm::Crc16Xmodem
+            | Algorithm::Crc16Arc
+            | Algorithm::Crc16Modbus
+            | Algorithm::Crc16Kermit
+            | Algorithm::Sum16
+            | Algorithm::Sum16Words
+            | Algorithm::Fletcher16 => 2,
+            _ => 4,
+        }
+    }
+
+    /// Ways the checksum may be stored. Sums are often stored negated so that
+    /// the image and checksum sum to zero, sums and XORs inverted.
+    fn transforms(&self) -> &'static [Transform] {
+        match self {
+            Algorithm::Sum8
+            | Algorithm::Sum16
+            | Algorithm::Sum32
+            | Algorithm::Sum16Words
+            | Algorithm::Sum32Words => &[Transform::Plain, Transform::Negated, Transform::Inverted],
+            Algorithm::Xor8 | Algorithm::Xor32Words => &[Transform::Plain, Transform::Inverted],
+            _ => &[Transform::Plain],
+        }
+    }
+
+    fn crc(&self) -> Option<&'static Crc> {
+        match self {
+            Algorithm::Crc16CcittFalse => Some(&CRC16_CCITT_FALSE),
+            Algorithm::Crc16Xmodem => Some(&CRC16_XMODEM),
+            Algorithm::Crc16Arc => Some(&CRC16_ARC),
+            Algorithm::Crc16Modbus => Some(&CRC16_MODBUS),
+            Algorithm::Crc16Kermit => Some(&CRC16_KERMIT),
+            Algorithm::Crc32Mpeg2 => Some(&CRC32_MPEG2),
+            Algorithm::Crc32Bzip2 => Some(&CRC32_BZIP2),
+            _ => None,
+        }
+    }
+
+    pub fn compute(&self, data: &[u8]) -> u32 {
+        if let Some(crc) = self.crc() {
+            return crc.compute(data);
+        }
+        let sum = |data: &[u8]| data.iter().fold(0u32, |sum, &b| sum.wrapping_add(b as u32));
+        match self {
+            Algorithm::Crc32 => crc32fast::hash(data),
+            Algorithm::Sum8 => sum(data) & 0xFF,
+            Algorithm::Sum16 => sum(data) & 0xFFFF,
+            Algorithm::Sum32 => sum(data),
+            Algorithm::Sum16Words => words::<2>(data).fold(0u32, u32::wrapping_add) & 0xFFFF,
+            Algorithm::Sum32Words => words::<4>(data).fold(0u32, u32::wrapping_add),
+            Algorithm::Xor8 => data.iter().fold(0, |xor, &b| xor ^ b) as u32,
+            Algorithm::Xor32Words => words::<4>(data).fold(0, |xor, word| xor ^ word),
+            Algorithm::Fletcher16 => {
+                let (a, b) = data.iter().fold((0u32, 0u32), |(a, b), &byte| {
+                    let a = (a + byte as u32) % 255;
+                    (a, (b + a) % 255)
+                });
+                (b << 8) | a
+            }
+            Algorithm::Fletcher32 => {
+                let (a, b) = words::<2>(data).fold((0u64, 0u64), |(a, b), word| {
+                    let a = (a + word as u64) % 65535;
+                    (a, (b + a) % 65535)
+                });
+                ((b << 16) | a) as u32
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Where a checksum could be stored, relative to its component.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Location {
+    /// First bytes of the body, the checksum covering the rest of it
+    BodyStart,
+    /// Last bytes of the body, before any padding
+    BodyEnd,
+    /// Last bytes of the padding of padded sections
+    PaddingEnd,
+    /// The 4 bytes between a section tag and its length, zero in known BLOBs
+    Tag,
+    /// The bytes skipped between the trailer block and terminator of `GASD` and `SUSD`
+    TrailerExtra,
+    /// First of the 8 bytes between the GPS and sound DB lengths of 24-byte
+    /// BLOB headers, zero in known BLOBs
+    HeaderSpareStart,
+    /// Last of the 8 spare bytes of 24-byte BLOB headers
+    HeaderSpareEnd,
+}
+
+impl Location {
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            Location::BodyStart => "body_start",
+            Location::BodyEnd => "body_end",
+            Location::PaddingEnd => "padding_end",
+            Location::Tag => "tag",
+            Location::TrailerExtra => "trailer_extra",
+            Location::HeaderSpareStart => "header_spare_start",
+            Location::HeaderSpareEnd => "header_spare_end",
+        }
+    }
+}
+
+/// Bytes covered by a checksum, leaving out the checksum itself.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Coverage {
+    /// The declared payload, without the trailer block of sound and GPS DBs
+    Body,
+    /// The payload including its padding, or the trailer block of DBs
+    Payload,
+}
+
+impl Coverage {
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            Coverage::Body => "body",
+            Coverage::Payload => "payload",
+        }
+    }
+}
+
+/// How a checksum is stored.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Transform {
+    Plain,
+    /// Two's complement, making the sum over the image and checksum zero
+    Negated,
+    /// One's complement
+    Inverted,
+}
+
+impl Transform {
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            Transform::Plain => "plain",
+            Transform::Negated => "negated",
+            Transform::Inverted => "inverted",
+        }
+    }
+
+    fn apply(&self, value: u32, width: usize) -> u32 {
+        let mask = u32::MAX >> (32 - width * 8);
+        match self {
+            Transform::Plain => value,
+            Transform::Negated => value.wrapping_neg() & mask,
+            Transform::Inverted => !value & mask,
+        }
+    }
+}
+
+/// A checksum found in a component.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Candidate {
+    pub component: &'static str,
+    pub algorithm: Algorithm,
+    pub location: Location,
+    pub coverage: Coverage,
+    /// Byte order of the stored checksum, always little endian for 8-bit ones
+    pub big_endian: bool,
+    pub transform: Transform,
+}
+
+impl Candidate {
+    pub fn describe(&self) -> String {
+        let mut algorithm = self.algorithm.to_name().to_string();
+        if self.transform != Transform::Plain {
+            algorithm = format!("{} {}", self.transform.to_name(), algorithm);
+        }
+        if self.algorithm.width() > 1 {
+            let order = if self.big_endian { "big" } else { "little" };
+            algorithm = format!("{} ({} endian)", algorithm, order);
+        }
+        format!(
+            "{}: {} at {} over {}",
+            self.component,
+            algorithm,
+            self.location.to_name(),
+            self.coverage.to_name()
+        )
+    }
+}
+
+/// Checksum locations of `component` as (location, coverage, stored bytes,
+/// covered range of the BLOB) for checksums `width` bytes long.
+fn locations(
+    firmware: &UnidenFirmware,
+    component: &ComponentView,
+    width: usize,
+) -> Vec<(Location, Coverage, [usize; 2], [usize; 2])> {
+    let start = component.file().info().base().offset() as usize;
+    let payload = component.payload().len();
+    let trimmed = component.slice(ExtractMode::Trimmed).len();
+    let is_db = db_terminator(component.kind().to_name()).is_some();
+    let body = if is_db {
+        trimmed.saturating_sub(12)
+    } else {
+        trimmed
+    };
+
+    let mut locations = vec![];
+    if body >= width * 2 {
+        locations.push((
+            Location::BodyStart,
+            Coverage::Body,
+            [start, start + width],
+            [start + width, start + body],
+        ));
+        locations.push((
+            Location::BodyEnd,
+            Coverage::Body,
+            [start + body - width, start + body],
+            [start, start + body - width],
+        ));
+    }
+    if !is_db && payload > body && payload >= width * 2 {
+        locations.push((
+            Location::PaddingEnd,
+            Coverage::Payload,
+            [start + payload - width, start + payload],
+            [start, start + payload - width],
+        ));
+    }
+
+    let mut outside = vec![];
+    if component.tag().is_some() && start >= 8 {
+        outside.push((Location::Tag, [start - 8, start - 8 + width]));
+    }
+    // Sections described by the header may have their checksum next to their length
+    let has_spare = Header::parse(&firmware.buffer).is_ok_and(|header| header.includes_sound_db);
+    if component.tag().is_none() && has_spare {
+        outside.push((Location::HeaderSpareStart, [12, 12 + width]));
+        outside.push((Location::HeaderSpareEnd, [20 - width, 20]));
+    }
+    if let Some(trailer) = component.trailer() {
+        let extra = trailer.extra();
+        if extra.len() >= width {
+            let at = trailer.offset() as usize + 12;
+            outside.push((Location::TrailerExtra, [at, at + width]));
+        }
+    }
+    for (location, stored) in outside {
+        if body > 0 {
+            locations.push((location, Coverage::Body, stored, [start, start + body]));
+        }
+        if payload > body {
+            locations.push((
+                location,
+                Coverage::Payload,
+                stored,
+                [start, start + payload],
+            ));
+        }
+    }
+    locations.retain(|(_, _, stored, _)| stored[1] <= firmware.buffer.len());
+    locations
+}
+
+/// Checksums matching the contents of each component of `firmware`, trying
+/// every [`Algorithm`] at every [`Location`].
+///
+/// Locations holding only zeroes or 0xFF are skipped. Short checksums match by
+/// chance now and then, so candidates are best confirmed across several BLOBs
+/// with [`find_in_corpus`].
+pub fn find_checksums(firmware: &UnidenFirmware) -> Vec<Candidate> {
+    let buffer = &firmware.buffer;
+    let mut found = vec![];
+    for component in firmware.components() {
+        let mut computed: BTreeMap<(Algorithm, [usize; 2]), u32> = BTreeMap::new();
+        for algorithm in Algorithm::ALL {
+            let width = algorithm.width();
+            for (location, coverage, stored, range) in locations(firmware, &component, width) {
+                let value = *computed
+                    .entry((algorithm, range))
+                    .or_insert_with(|| algorithm.compute(&buffer[range[0]..range[1]]));
+
+                let bytes = &buffer[stored[0]..stored[1]];
+                // Unused fields and padding, not worth reporting whatever matches them
+                if bytes.iter().all(|&b| b == 0) || bytes.iter().all(|&b| b == 0xFF) {
+                    continue;
+                }
+                let mut word = [0; 4];
+                word[..width].copy_from_slice(bytes);
+                let little = u32::from_le_bytes(word);
+                word[..width].reverse();
+                let big = u32::from_le_bytes(word);
+
+                for &transform in algorithm.transforms() {
+                    let expected = transform.apply(value, width);
+                    let orders: &[bool] = if width == 1 { &[false] } else { &[false, true] };
+                    for &big_endian in orders {
+                        if expected == if big_endian { big } else { little } {
+                            found.push(Candidate {
+                                component: component.kind().to_name(),
+                                algorithm,
+                                location,
+                                coverage,
+                                big_endian,
+                                transform,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    found
+}
+
+/// A checksum found in some BLOBs of a corpus.
+pub struct CorpusMatch {
+    pub candidate: Candidate,
+    /// BLOBs where the checksum matched
+    pub matches: usize,
+    /// BLOBs holding the component
+    pub blobs: usize,
+}
+
+impl CorpusMatch {
+    /// Whether the checksum matched in every BLOB holding the component.
+    pub fn is_consistent(&self) -> bool {
+        self.matches == self.blobs
+    }
+}
+
+/// Checksums found in the BLOBs of `firmwares`, those matching in the most
+/// BLOBs first.
+pub fn find_in_corpus(firmwares: &[UnidenFirmware]) -> Vec<CorpusMatch> {
+    let mut blobs: BTreeMap<&'static str, usize> = BTreeMap::new();
+    let mut matches: BTreeMap<Candidate, usize> = BTreeMap::new();
+    for firmware in firmwares {
+        for component in firmware.components() {
+            *blobs.entry(component.kind().to_name()).or_default() += 1;
+        }
+        for candidate in find_checksums(firmware) {
+            *matches.entry(candidate).or_default() += 1;
+        }
+    }
+
+    let mut corpus: Vec<_> = matches
+        .into_iter()
+        .map(|(candidate, matches)| CorpusMatch {
+            candidate,
+            matches,
+            blobs: blobs[candidate.component],
+        })
+        .collect();
+    corpus.sort_by(|a, b| {
+        let ratio = |m: &CorpusMatch| m.matches as f64 / m.blobs as f64;
+        ratio(b)
+            .total_cmp(&ratio(a))
+            .then(b.matches.cmp(&a.matches))
+            .then(a.candidate.cmp(&b.candidate))
+    });
+    corpus
+}
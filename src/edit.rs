@@ -0,0 +1,327 @@
+use crate::file::{db_terminator, FWFile, FWFileKind, UnidenFirmware};
+use crate::format::{
+    decode_old_model, encode_old_model, padding_block, terminator_for_component, RDModel,
+    SOUND_DB_KEY,
+};
+use std::io;
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Where the declared length of a component is stored.
+enum LengthField {
+    /// 24-bit field sharing the first header word with the sound DB flag.
+    UiNuHeader,
+    /// 32-bit field at a fixed position in the legacy header.
+    Header(usize),
+    /// 32-bit field in the 12-byte tag that precedes the section.
+    Tag(usize),
+}
+
+fn length_field(file: &FWFile) -> LengthField {
+    match file.kind {
+        FWFileKind::UiNu(_) => LengthField::UiNuHeader,
+        FWFileKind::DspNu(_) => LengthField::Header(4),
+        FWFileKind::GpsNu(_) => LengthField::Header(8),
+        FWFileKind::SoundDbnu(_) => LengthField::Header(20),
+        _ => LengthField::Tag(file.info.base().offset as usize - 4),
+    }
+}
+
+pub(crate) fn check_rd_version(version: i64) -> io::Result<i16> {
+    if (0..0x400).contains(&version) {
+        Ok(version as i16)
+    } else {
+        Err(invalid_input(format!(
+            "Version {} does not fit in 10 bits",
+            version
+        )))
+    }
+}
+
+fn reparse(data: &[u8]) -> io::Result<UnidenFirmware> {
+    let mut firmware = UnidenFirmware::from_bytes(data);
+    firmware.read_buffer()?;
+    Ok(firmware)
+}
+
+impl UnidenFirmware {
+    pub fn find_file(&self, name: &str) -> Option<&FWFile> {
+        self.files.iter().find(|file| file.kind.to_name() == name)
+    }
+
+    /// Rebuild the BLOB with the payload of component `name` swapped for `data`.
+    ///
+    /// The new payload is padded the same way the parser expects, the length
+    /// field in the header (or section tag) is rewritten, and the trailer is
+    /// kept as-is unless `version` is given. Sound and GPS DBs may end with
+    /// their terminator, padded components are taken without their trailer.
+    pub fn replace_component(
+        &self,
+        name: &str,
+        data: &[u8],
+        version: Option<i16>,
+    ) -> io::Result<Vec<u8>> {
+        let file = self
+            .find_file(name)
+            .ok_or_else(|| invalid_input(format!("Component not found: {}", name)))?;
+        if data.is_empty() {
+            return Err(invalid_input("Replacement component is empty".into()));
+        }
+
+        let info = file.info.base();
+        let offset = info.offset as usize;
+        let old_end = offset + info.length as usize;
+
+        // databases built with their terminator attached are accepted as-is
+        let data = match db_terminator(name) {
+            Some((skip, terminator)) if data.ends_with(terminator.as_bytes()) => {
+                &data[..data.len().saturating_sub(skip + terminator.len())]
+            }
+            _ => data,
+        };
+        // padded images written by `extract --raw` end with the trailer of the
+        // section, and their padding can't be told apart from the payload
+        if let (Some(_), Some(terminator)) = (padding_block(name), terminator_for_component(name)) {
+            if data.ends_with(terminator.as_bytes()) {
+                return Err(invalid_input(format!(
+                    "Replacement {} ends with its trailer and terminator, give the image written by `extract --trim` instead",
+                    name
+                )));
+            }
+        }
+
+        let mut payload = data.to_vec();
+        if let Some(block) = padding_block(name) {
+            // keep whatever filler the original section was padded with
+            let fill = match info.length {
+                0 => 0xFF,
+                _ => self.buffer[old_end - 1],
+            };
+            let block = block as usize;
+            payload.resize((data.len() / block + 1) * block, fill);
+        } else if data.len() < 12 {
+            return Err(invalid_input(format!(
+                "{} must include its 12-byte trailer block",
+                name
+            )));
+        }
+
+        let declared = data.len();
+        let mut out = Vec::with_capacity(self.buffer.len() - info.length as usize + payload.len());
+        out.extend_from_slice(&self.buffer[..offset]);
+        out.extend_from_slice(&payload);
+        out.extend_from_slice(&self.buffer[old_end..]);
+
+        match length_field(file) {
+            LengthField::UiNuHeader => {
+                if declared > 0xFFFFFF {
+                    return Err(invalid_input(format!(
+                        "ui_nu is limited to {} bytes",
+                        0xFFFFFF
+                    )));
+                }
+                let word = u32::from_le_bytes(out[0..4].try_into().unwrap());
+                let word = (word & 0xFF000000) | declared as u32;
+                out[0..4].copy_from_slice(&word.to_le_bytes());
+            }
+            LengthField::Header(pos) | LengthField::Tag(pos) => {
+                let declared = i32::try_from(declared)
+                    .map_err(|_| invalid_input(format!("{} is too large", name)))?;
+                out[pos..pos + 4].copy_from_slice(&declared.to_le_bytes());
+            }
+        }
+
+        if let Some(version) = version {
+            if padding_block(name).is_none() {
+                return Err(invalid_input(format!(
+                    "The version of {} is stored in its encoded trailer block",
+                    name
+                )));
+            }
+            let version = check_rd_version(version as i64)?;
+            let pos = offset + payload.len();
+            let word = i16::from_le_bytes(out[pos..pos + 2].try_into().unwrap());
+            let word = (word & !0x3FF) | version;
+            out[pos..pos + 2].copy_from_slice(&word.to_le_bytes());
+        }
+
+        self.validate_rebuilt(&out, name, payload.len())?;
+        Ok(out)
+    }
+
+    /// Rebuild the BLOB with a new tagged section holding `section`, placed before
+    /// the `NMGF` record if there is one.
+    ///
+    /// `section` is the payload followed by its terminator, as built by
+    /// [`crate::gpsdb::GpsDb::build_section`] or [`crate::sound::SoundDb::build_section`].
+    pub fn insert_section(&self, tag: &str, name: &str, section: &[u8]) -> io::Result<Vec<u8>> {
+        if tag.len() != 4 {
+            return Err(invalid_input(format!("Invalid section tag: {}", tag)));
+        }
+        if self.find_file(name).is_some() {
+            return Err(invalid_input(format!("BLOB already contains {}", name)));
+        }
+        let (skip, terminator) = db_terminator(name)
+            .ok_or_else(|| invalid_input(format!("Can't insert {} as a section", name)))?;
+        let length = section
+            .len()
+            .checked_sub(skip + terminator.len())
+            .filter(|_| section.ends_with(terminator.as_bytes()))
+            .ok_or_else(|| invalid_input(format!("{} must end with {}", name, terminator)))?;
+        let length =
+            i32::try_from(length).map_err(|_| invalid_input(format!("{} is too large", name)))?;
+
+        let merge_file = self
+            .metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.is_merged());
+        let position = if merge_file {
+            self.buffer.len() - 12
+        } else {
+            self.buffer.len()
+        };
+
+        let mut out = Vec::with_capacity(self.buffer.len() + 12 + section.len());
+        out.extend_from_slice(&self.buffer[..position]);
+        out.extend_from_slice(tag.as_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&length.to_le_bytes());
+        out.extend_from_slice(section);
+        out.extend_from_slice(&self.buffer[position..]);
+
+        let rebuilt = reparse(&out)?;
+        if rebuilt.find_file(name).is_none() || rebuilt.files.len() != self.files.len() + 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Rebuilt BLOB does not contain the new {}", name),
+            ));
+        }
+        Ok(out)
+    }
+
+    /// Rebuild the BLOB with the trailer version of component `name` set to `version`.
+    ///
+    /// Component trailers hold a 10-bit version next to the 6-bit model read by
+    /// [`RDModel::from_data`], which can be changed too. Sound DBs keep the same
+    /// packing in their encoded trailer block, while GPS DBs store a plain 32-bit
+    /// version.
+    pub fn set_version(
+        &self,
+        name: &str,
+        version: i64,
+        model: Option<RDModel>,
+    ) -> io::Result<Vec<u8>> {
+        let file = self
+            .find_file(name)
+            .ok_or_else(|| invalid_input(format!("Component not found: {}", name)))?;
+        let info = file.info.base();
+        let end = (info.offset + info.length) as usize;
+        let mut out = self.buffer.clone();
+
+        match file.kind {
+            FWFileKind::GpsDb(_) | FWFileKind::GpsDbSecond(_) => {
+                if model.is_some() {
+                    return Err(invalid_input(format!("{} has no model bits", name)));
+                }
+                let version = i32::try_from(version)
+                    .ok()
+                    .filter(|v| *v >= 0)
+                    .ok_or_else(|| {
+                        invalid_input(format!("Version {} does not fit in 31 bits", version))
+                    })?;
+                out[end - 8..end - 4].copy_from_slice(&version.to_le_bytes());
+            }
+            FWFileKind::SoundDbnu(_) | FWFileKind::SoundDbla1(_) | FWFileKind::SoundDbla2(_) => {
+                if model.is_some() {
+                    return Err(invalid_input(format!("{} has no model bits", name)));
+                }
+                let version = check_rd_version(version)?;
+                let word = decode_old_model(SOUND_DB_KEY, &out, end - 12, 4);
+                let word = i32::from_le_bytes(word.try_into().unwrap());
+                let word = (word & !0x3FF) | version as i32;
+                let encoded = encode_old_model(SOUND_DB_KEY, &word.to_le_bytes(), 0, 4);
+                out[end - 12..end - 8].copy_from_slice(&encoded);
+            }
+            _ => {
+                let version = check_rd_version(version)?;
+                let mut word = i16::from_le_bytes(out[end..end + 2].try_into().unwrap());
+                if let Some(model) = model {
+                    let model = model as u8;
+                    if model > 0x3F {
+                        return Err(invalid_input(format!(
+                            "Model {} does not fit in the trailer's model bits",
+                            RDModel::from(model).to_name()
+                        )));
+                    }
+                    word = (word & 0x3FF) | ((model as i16) << 10);
+                }
+                word = (word & !0x3FF) | version;
+                out[end..end + 2].copy_from_slice(&word.to_le_bytes());
+            }
+        }
+
+        let rebuilt = reparse(&out)?;
+        match rebuilt.find_file(name) {
+            Some(file) if file.info.base().version as i64 == version => Ok(out),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Rebuilt {} does not have the expected version", name),
+            )),
+        }
+    }
+
+    /// Rebuild the BLOB with the format version in its `NMGF` record set to `version`.
+    pub fn set_format_version(&self, version: i32) -> io::Result<Vec<u8>> {
+        let metadata = self.metadata.as_ref();
+        if !metadata.is_some_and(|metadata| metadata.is_merged()) {
+            return Err(invalid_input("BLOB has no NMGF record".into()));
+        }
+        if version < 0 {
+            return Err(invalid_input(format!(
+                "Invalid format version: {}",
+                version
+            )));
+        }
+
+        let mut out = self.buffer.clone();
+        let len = out.len();
+        out[len - 4..].copy_from_slice(&version.to_le_bytes());
+
+        let rebuilt = reparse(&out)?;
+        match rebuilt.metadata {
+            Some(metadata) if metadata.format_version == Some(version) => Ok(out),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Rebuilt BLOB does not have the expected format version",
+            )),
+        }
+    }
+
+    /// Re-parse a rebuilt BLOB and check it still has the same components.
+    fn validate_rebuilt(&self, data: &[u8], name: &str, length: usize) -> io::Result<()> {
+        let rebuilt = reparse(data)?;
+
+        let names = |fw: &UnidenFirmware| {
+            fw.files
+                .iter()
+                .map(|file| file.kind.to_name())
+                .collect::<Vec<_>>()
+        };
+        if names(self) != names(&rebuilt) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Rebuilt BLOB has a different set of components",
+            ));
+        }
+
+        match rebuilt.find_file(name) {
+            Some(file) if file.info.base().length as usize == length => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Rebuilt {} does not have the expected length", name),
+            )),
+        }
+    }
+}
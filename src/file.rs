@@ -1,37 +1,112 @@
+pub use crate::format::db_terminator;
 use crate::format::{
-    decode_old_model, rd_version, GpsDbCountry, GpsDbType, RDModel, NEW_FILE_GPS_DB_IDENTIFY_STR,
-    OLD_FILE_GPS_DB_IDENTIFY_STR, OLD_IL_GPS_DB_KEY, OLD_NZ_GPS_DB_KEY, OLD_US_GPS_DB_KEY,
-    SOUND_DB_KEY,
+    component_for_terminator, trailer_length, GpsDbCountry, GpsDbType, RDModel, UnknownName,
 };
-use crate::util::{alter_length, CursorHelper, TrackingCursor};
+use crate::trailer::Trailer;
+#[cfg(feature = "serde")]
+use crate::util::to_hex;
+use crate::util::{CursorHelper, TrackingCursor};
 use rust_lapper::{Interval, Lapper};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::io::Write;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::{fs, io, path};
+pub use ufwt_core::section::FormatGeneration;
+use ufwt_core::section::{component_for_tag, is_section_start, GpsDbInfo, Section, Sections};
 
 type Iv = Interval<u64, ()>;
 
-#[derive(Clone, Copy)]
-struct FileInfoBase {
-    length: i32,
-    offset: i32,
-    version: i32,
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileInfoBase {
+    pub(crate) length: i32,
+    pub(crate) declared_length: i32,
+    pub(crate) offset: i32,
+    pub(crate) version: i32,
+}
+
+impl FileInfoBase {
+    /// Length of the payload including its padding.
+    pub fn length(&self) -> i32 {
+        self.length
+    }
+
+    /// Length of the payload as declared in the header or section tag, before padding.
+    pub fn declared_length(&self) -> i32 {
+        self.declared_length
+    }
+
+    pub fn offset(&self) -> i32 {
+        self.offset
+    }
+
+    pub fn version(&self) -> i32 {
+        self.version
+    }
 }
 
 #[derive(Clone, Copy)]
-struct GpsDbFileInfo {
-    info: FileInfoBase,
-    poi: i32,
-    file_type: GpsDbType,
-    country: Option<GpsDbCountry>,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GpsDbFileInfo {
+    pub(crate) info: FileInfoBase,
+    pub(crate) poi: i32,
+    pub(crate) file_type: GpsDbType,
+    pub(crate) country: Option<GpsDbCountry>,
+}
+
+impl GpsDbFileInfo {
+    pub fn info(&self) -> &FileInfoBase {
+        &self.info
+    }
+
+    pub fn poi(&self) -> i32 {
+        self.poi
+    }
+
+    pub fn file_type(&self) -> GpsDbType {
+        self.file_type
+    }
+
+    pub fn country(&self) -> Option<GpsDbCountry> {
+        self.country
+    }
+
+    /// Combine `info` with the trailer block of a GPS DB, which holds its version.
+    fn new(info: FileInfoBase, gps_db: GpsDbInfo) -> GpsDbFileInfo {
+        GpsDbFileInfo {
+            info: FileInfoBase {
+                version: gps_db.version,
+                ..info
+            },
+            poi: gps_db.poi,
+            file_type: gps_db.file_type,
+            country: gps_db.country,
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum FileInfo {
     Base(FileInfoBase),
     GpsDb(GpsDbFileInfo),
 }
 
+impl FileInfo {
+    pub fn base(&self) -> &FileInfoBase {
+        match self {
+            FileInfo::Base(base) => base,
+            FileInfo::GpsDb(gps_db) => &gps_db.info,
+        }
+    }
+}
+
+/// Serialized with [`FWFileKind::to_name`] as the key of its info.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum FWFileKind {
     UiNu(FileInfo),
     UiStm(FileInfo),
@@ -55,27 +130,150 @@ pub enum FWFileKind {
 }
 
 impl FWFileKind {
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            FWFileKind::UiNu(_) => "ui_nu",
+            FWFileKind::UiStm(_) => "ui_stm",
+            FWFileKind::UiNu2(_) => "ui_nu2",
+            FWFileKind::DspNu(_) => "dsp_nu",
+            FWFileKind::DspStm(_) => "dsp_stm",
+            FWFileKind::DspNu2(_) => "dsp_nu2",
+            FWFileKind::DspNu3(_) => "dsp_nu3",
+            FWFileKind::GpsNu(_) => "gps_nu",
+            FWFileKind::GpsStm(_) => "gps_stm",
+            FWFileKind::GpsNu2(_) => "gps_nu2",
+            FWFileKind::GpsNu3(_) => "gps_nu3",
+            FWFileKind::SoundDbnu(_) => "sound_dbnu",
+            FWFileKind::SoundDbla1(_) => "sound_dbla1",
+            FWFileKind::SoundDbla2(_) => "sound_dbla2",
+            FWFileKind::GpsDb(_) => "gps_db",
+            FWFileKind::GpsDbSecond(_) => "gps_db_second",
+            FWFileKind::Ble(_) => "ble",
+            FWFileKind::Keypad(_) => "keypad",
+            FWFileKind::LaserIf(_) => "laser_if",
+        }
+    }
+
     pub fn to_file_name(&self) -> String {
+        format!("{}.bin", self.to_name())
+    }
+
+    /// Inverse of [`FWFileKind::to_name`]. GPS DBs need [`FileInfo::GpsDb`] info.
+    pub fn from_name(name: &str, info: FileInfo) -> Option<FWFileKind> {
+        Some(match (name, info) {
+            ("ui_nu", _) => FWFileKind::UiNu(info),
+            ("ui_stm", _) => FWFileKind::UiStm(info),
+            ("ui_nu2", _) => FWFileKind::UiNu2(info),
+            ("dsp_nu", _) => FWFileKind::DspNu(info),
+            ("dsp_stm", _) => FWFileKind::DspStm(info),
+            ("dsp_nu2", _) => FWFileKind::DspNu2(info),
+            ("dsp_nu3", _) => FWFileKind::DspNu3(info),
+            ("gps_nu", _) => FWFileKind::GpsNu(info),
+            ("gps_stm", _) => FWFileKind::GpsStm(info),
+            ("gps_nu2", _) => FWFileKind::GpsNu2(info),
+            ("gps_nu3", _) => FWFileKind::GpsNu3(info),
+            ("sound_dbnu", _) => FWFileKind::SoundDbnu(info),
+            ("sound_dbla1", _) => FWFileKind::SoundDbla1(info),
+            ("sound_dbla2", _) => FWFileKind::SoundDbla2(info),
+            ("gps_db", FileInfo::GpsDb(info)) => FWFileKind::GpsDb(info),
+            ("gps_db_second", FileInfo::GpsDb(info)) => FWFileKind::GpsDbSecond(info),
+            ("ble", _) => FWFileKind::Ble(info),
+            ("keypad", _) => FWFileKind::Keypad(info),
+            ("laser_if", _) => FWFileKind::LaserIf(info),
+            _ => return None,
+        })
+    }
+}
+
+impl FromStr for FWFileKind {
+    type Err = UnknownName;
+
+    /// Accepts component names as in [`FWFileKind::to_name`] and section tags,
+    /// e.g. `GPSD`, leaving the info empty.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = component_for_tag(name.as_bytes()).unwrap_or(name);
+        let info = if name.starts_with("gps_db") {
+            FileInfo::GpsDb(GpsDbFileInfo {
+                info: FileInfoBase::default(),
+                poi: 0,
+                file_type: GpsDbType::Unknown,
+                country: None,
+            })
+        } else {
+            FileInfo::Base(FileInfoBase::default())
+        };
+        FWFileKind::from_name(name, info).ok_or(UnknownName("component"))
+    }
+}
+
+/// Part of a component written by [`UnidenFirmware::extract_to`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExtractMode {
+    /// The payload including its padding
+    Padded,
+    /// Only the payload length declared in the header or section tag
+    Trimmed,
+    /// The padded payload followed by its trailer and terminator
+    Raw,
+}
+
+impl ExtractMode {
+    pub fn to_name(&self) -> &'static str {
         match self {
-            FWFileKind::UiNu(_) => "ui_nu.bin".into(),
-            FWFileKind::UiStm(_) => "ui_stm.bin".into(),
-            FWFileKind::UiNu2(_) => "ui_nu2.bin".into(),
-            FWFileKind::DspNu(_) => "dsp_nu.bin".into(),
-            FWFileKind::DspStm(_) => "dsp_stm.bin".into(),
-            FWFileKind::DspNu2(_) => "dsp_nu2.bin".into(),
-            FWFileKind::DspNu3(_) => "dsp_nu3.bin".into(),
-            FWFileKind::GpsNu(_) => "gps_nu.bin".into(),
-            FWFileKind::GpsStm(_) => "gps_stm.bin".into(),
-            FWFileKind::GpsNu2(_) => "gps_nu2.bin".into(),
-            FWFileKind::GpsNu3(_) => "gps_nu3.bin".into(),
-            FWFileKind::SoundDbnu(_) => "sound_dbnu.bin".into(),
-            FWFileKind::SoundDbla1(_) => "sound_dbla1.bin".into(),
-            FWFileKind::SoundDbla2(_) => "sound_dbla2.bin".into(),
-            FWFileKind::GpsDb(_) => "gps_db.bin".into(),
-            FWFileKind::GpsDbSecond(_) => "gps_db_second.bin".into(),
-            FWFileKind::Ble(_) => "ble.bin".into(),
-            FWFileKind::Keypad(_) => "keypad.bin".into(),
-            FWFileKind::LaserIf(_) => "laser_if.bin".into(),
+            ExtractMode::Padded => "padded",
+            ExtractMode::Trimmed => "trimmed",
+            ExtractMode::Raw => "raw",
+        }
+    }
+}
+
+/// `manifest.json` written next to the components by [`UnidenFirmware::extract_to`].
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct Manifest {
+    generation: &'static str,
+    format_version: Option<i32>,
+    model: &'static str,
+    mode: &'static str,
+    components: Vec<ManifestComponent>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct ManifestComponent {
+    name: &'static str,
+    file: String,
+    offset: i32,
+    length: i32,
+    declared_length: i32,
+    version: i32,
+    damaged: bool,
+    trailer: Option<ManifestTrailer>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct ManifestTrailer {
+    offset: u64,
+    /// Hex encoded
+    raw: String,
+    model: Option<&'static str>,
+    version: i32,
+    /// Hex encoded
+    extra: String,
+    terminator: String,
+}
+
+#[cfg(feature = "serde")]
+impl ManifestTrailer {
+    fn new(trailer: &Trailer) -> ManifestTrailer {
+        ManifestTrailer {
+            offset: trailer.offset(),
+            raw: to_hex(trailer.raw()),
+            model: trailer.model().map(|model| model.to_name()),
+            version: trailer.version(),
+            extra: to_hex(trailer.extra()),
+            terminator: trailer.terminator().to_owned(),
         }
     }
 }
@@ -83,6 +281,29 @@ impl FWFileKind {
 pub struct FWFile {
     pub(crate) kind: FWFileKind,
     pub(crate) info: FileInfo,
+    pub(crate) damaged: bool,
+    pub(crate) trailer: Option<Trailer>,
+}
+
+impl FWFile {
+    pub fn kind(&self) -> &FWFileKind {
+        &self.kind
+    }
+
+    pub fn info(&self) -> &FileInfo {
+        &self.info
+    }
+
+    /// Whether the component was recovered by lenient parsing and may be incomplete.
+    pub fn damaged(&self) -> bool {
+        self.damaged
+    }
+
+    /// Trailer following the component, missing only for damaged components
+    /// whose end couldn't be found.
+    pub fn trailer(&self) -> Option<&Trailer> {
+        self.trailer.as_ref()
+    }
 }
 
 pub fn handle_gpsdb_file_info(file: &FWFileKind) -> Option<&GpsDbFileInfo> {
@@ -117,34 +338,251 @@ pub fn handle_file_info(file: &FWFileKind) -> Option<&FileInfo> {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FWMetadata {
     pub model: RDModel,
-    pub format_version: i32,
-    pub new_merge_file: bool,
+    pub generation: FormatGeneration,
+    /// `NMGF` format version, only present in merged BLOBs
+    pub format_version: Option<i32>,
 }
 
-/// (offset, version, end string)
-fn parse_file_basic(cursor: &mut TrackingCursor, length: i32) -> io::Result<(i32, i32, String)> {
-    let offset = cursor.position() as i32;
-    cursor.seek(length as u64);
+impl FWMetadata {
+    pub fn is_merged(&self) -> bool {
+        self.format_version.is_some()
+    }
+}
 
-    let arr = cursor.read_n(9)?;
-    let version = rd_version(i16::from_le_bytes(arr[0..2].try_into().unwrap())) as i32;
-    let end_string = String::from_utf8(arr[2..].to_vec()).unwrap();
-    Ok((offset, version, end_string))
+/// Decode the 12-byte trailer block ending a GPS DB: POI count, version and
+/// identify string. Returns `None` if the identify string is unknown.
+pub(crate) fn read_gps_db_info(arr: &[u8], info: FileInfoBase) -> Option<GpsDbFileInfo> {
+    GpsDbInfo::parse(arr).map(|gps_db| GpsDbFileInfo::new(info, gps_db))
 }
 
-macro_rules! stfu {
-    ($ex:expr) => {
-        String::from_utf8($ex.to_vec()).unwrap()
+/// Problem found while reading a BLOB in lenient mode.
+pub struct Diagnostic {
+    pub offset: u64,
+    pub message: String,
+}
+
+/// Section being read, recovered as damaged by lenient parsing if reading it fails.
+type Pending = Option<(&'static str, u64)>;
+
+/// Turn an error of the core parser into the I/O error `read_buffer` returns.
+fn io_error(error: ufwt_core::Error) -> io::Error {
+    let kind = match error {
+        ufwt_core::Error::Truncated { .. } => io::ErrorKind::UnexpectedEof,
+        _ => io::ErrorKind::InvalidData,
     };
+    io::Error::new(kind, error.to_string())
+}
+
+/// What the section readers have found so far.
+struct ReadState {
+    files: Vec<FWFile>,
+    metadata: FWMetadata,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ReadState {
+    /// Keep a section read by the core parser.
+    fn push(&mut self, section: &Section) {
+        let base = FileInfoBase {
+            length: section.payload.len() as i32,
+            declared_length: section.declared_length,
+            offset: section.offset as i32,
+            version: section.trailer.version,
+        };
+        let info = match section.gps_db {
+            Some(gps_db) => FileInfo::GpsDb(GpsDbFileInfo::new(base, gps_db)),
+            None => FileInfo::Base(base),
+        };
+
+        if section.name == "ui_nu" {
+            self.metadata.model = section.trailer.model.unwrap();
+        }
+        self.files.push(FWFile {
+            kind: FWFileKind::from_name(section.name, info).unwrap(),
+            info,
+            damaged: false,
+            trailer: Some(Trailer::from(&section.trailer)),
+        });
+    }
+
+    /// Handle a section whose terminator isn't where its length says, but at
+    /// `declared`. Returns the position to carry on reading from.
+    ///
+    /// Lenient parsing looks for the section's own terminator further on, in case
+    /// its length is what got corrupted, and otherwise trusts the length. Either
+    /// way the section is kept as damaged and parsing carries on after it.
+    fn terminator_mismatch(
+        &mut self,
+        buffer: &[u8],
+        name: &'static str,
+        offset: usize,
+        declared: usize,
+        error: io::Error,
+    ) -> usize {
+        let trailer = db_terminator(name).map_or(2, |(skip, _)| skip);
+        let own = (offset + trailer..buffer.len())
+            .find_map(|pos| component_for_terminator(buffer.get(pos..pos + 7)?).map(|n| (pos, n)))
+            .filter(|(_, n)| *n == name)
+            .map(|(pos, _)| pos);
+        let terminator = own.unwrap_or(declared);
+
+        self.diagnostics.push(Diagnostic {
+            offset: offset as u64,
+            message: error.to_string(),
+        });
+        if own.is_some() {
+            self.diagnostics.push(Diagnostic {
+                offset: offset as u64,
+                message: format!("Recovered damaged {} up to its terminator", name),
+            });
+        }
+        let file = damaged_file(buffer, name, offset, terminator - trailer, true);
+        if name == "ui_nu" && own.is_some() {
+            if let Some(model) = file.trailer().and_then(|trailer| trailer.model) {
+                self.metadata.model = model;
+            }
+        }
+        self.files.push(file);
+        terminator + 7
+    }
+}
+
+/// Length declared for component `name` starting at `offset`, read from its
+/// section tag or from the header.
+fn declared_length_field(buffer: &[u8], name: &str, offset: usize) -> Option<i32> {
+    let word = |pos: usize| {
+        buffer
+            .get(pos..pos + 4)
+            .map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    match name {
+        "ui_nu" => word(0).map(|word| word & 0xFFFFFF),
+        "dsp_nu" => word(4),
+        "gps_nu" => word(8),
+        "sound_dbnu" => word(20),
+        _ => word(offset.checked_sub(4)?),
+    }
+}
+
+/// Build a damaged component spanning `offset..end`, reading its version from
+/// the trailer if it is still followed by its terminator.
+fn damaged_file(buffer: &[u8], name: &str, offset: usize, end: usize, terminated: bool) -> FWFile {
+    let base = FileInfoBase {
+        length: (end - offset) as i32,
+        declared_length: declared_length_field(buffer, name, offset)
+            .unwrap_or((end - offset) as i32),
+        offset: offset as i32,
+        version: -1,
+    };
+    // the trailer block of DBs must not reach before the start of the payload
+    let trailer = Trailer::read(buffer, name, end)
+        .filter(|trailer| terminated && trailer.offset >= offset as u64);
+
+    let info = if name.starts_with("gps_db") {
+        FileInfo::GpsDb(
+            trailer
+                .as_ref()
+                .and_then(|trailer| read_gps_db_info(&trailer.raw()[..12], base))
+                .unwrap_or(GpsDbFileInfo {
+                    info: base,
+                    poi: 0,
+                    file_type: GpsDbType::Unknown,
+                    country: None,
+                }),
+        )
+    } else {
+        FileInfo::Base(FileInfoBase {
+            version: trailer.as_ref().map_or(-1, |trailer| trailer.version),
+            ..base
+        })
+    };
+    FWFile {
+        kind: FWFileKind::from_name(name, info).unwrap(),
+        info,
+        damaged: true,
+        trailer,
+    }
+}
+
+/// Skip ahead to the next recognizable section tag or terminator after `error`
+/// occurred reading the section at `from`. The pending section, or the one
+/// named by the terminator, is kept as a damaged component.
+///
+/// Returns the position to carry on reading from.
+fn recover(
+    buffer: &[u8],
+    from: u64,
+    pending: Pending,
+    error: io::Error,
+    files: &mut Vec<FWFile>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> u64 {
+    let message = match pending {
+        Some((name, _)) if error.kind() == io::ErrorKind::UnexpectedEof => {
+            format!("{} runs past the end of the BLOB", name)
+        }
+        _ => error.to_string(),
+    };
+    diagnostics.push(Diagnostic {
+        offset: from,
+        message,
+    });
+
+    let from = from as usize;
+    let start = pending.map_or(from + 1, |(_, offset)| (offset as usize).max(from + 1));
+    for pos in start..buffer.len() {
+        if let Some(name) = buffer.get(pos..pos + 7).and_then(component_for_terminator) {
+            let skip = db_terminator(name).map_or(2, |(skip, _)| skip);
+            // without a pending section, `from` is the tag of the section ending here
+            let offset = pending.map_or(from + 12, |(_, offset)| offset as usize);
+            if let Some(end) = pos.checked_sub(skip).filter(|end| *end > offset) {
+                files.push(damaged_file(buffer, name, offset, end, true));
+                diagnostics.push(Diagnostic {
+                    offset: offset as u64,
+                    message: format!("Recovered damaged {} up to its terminator", name),
+                });
+            }
+            return (pos + 7) as u64;
+        }
+        if is_section_start(buffer, pos) {
+            if let Some((name, offset)) = pending.filter(|(_, offset)| pos > *offset as usize) {
+                files.push(damaged_file(buffer, name, offset as usize, pos, false));
+                diagnostics.push(Diagnostic {
+                    offset,
+                    message: format!("Recovered damaged {} up to the next section", name),
+                });
+            }
+            return pos as u64;
+        }
+    }
+
+    if let Some((name, offset)) = pending.filter(|(_, offset)| (*offset as usize) < buffer.len()) {
+        files.push(damaged_file(
+            buffer,
+            name,
+            offset as usize,
+            buffer.len(),
+            false,
+        ));
+        diagnostics.push(Diagnostic {
+            offset,
+            message: format!("Recovered truncated {}", name),
+        });
+    }
+    buffer.len() as u64
 }
 
 pub struct UnidenFirmware {
     pub metadata: Option<FWMetadata>,
     pub(crate) files: Vec<FWFile>,
     pub size: u64,
-    buffer: Vec<u8>,
+    pub(crate) buffer: Vec<u8>,
+    /// Keep reading past damaged sections instead of failing, see [`UnidenFirmware::diagnostics`]
+    pub lenient: bool,
+    pub(crate) diagnostics: Vec<Diagnostic>,
     read_intervals: Vec<Iv>,
     unread_intervals: Vec<Iv>,
 }
@@ -152,19 +590,70 @@ pub struct UnidenFirmware {
 impl UnidenFirmware {
     pub fn from(file_path: &PathBuf) -> Result<UnidenFirmware, String> {
         let buffer: Vec<u8> = fs::read(file_path).map_err(|e| e.to_string())?;
+        Ok(Self::from_buffer(buffer))
+    }
+
+    /// Read a whole BLOB from `reader`, e.g. stdin or an archive entry.
+    pub fn from_reader<R: io::Read>(mut reader: R) -> io::Result<UnidenFirmware> {
+        let mut buffer = vec![];
+        reader.read_to_end(&mut buffer)?;
+        Ok(Self::from_buffer(buffer))
+    }
+
+    /// Copy a BLOB already in memory, to be parsed by [`UnidenFirmware::read_buffer`].
+    pub fn from_bytes(data: &[u8]) -> UnidenFirmware {
+        Self::from_buffer(data.to_vec())
+    }
+
+    /// Take ownership of a BLOB already in memory, to be parsed by
+    /// [`UnidenFirmware::read_buffer`].
+    pub fn from_buffer(buffer: Vec<u8>) -> UnidenFirmware {
         let sz = buffer.len() as u64;
-        Ok(Self {
+        Self {
             metadata: None,
             files: vec![],
             size: sz,
-            buffer: buffer,
+            buffer,
+            lenient: false,
+            diagnostics: vec![],
             read_intervals: vec![],
             unread_intervals: vec![Iv {
                 start: 0,
                 stop: sz,
                 val: (),
             }],
-        })
+        }
+    }
+
+    pub fn files(&self) -> &[FWFile] {
+        &self.files
+    }
+
+    /// Problems skipped over by a lenient [`UnidenFirmware::read_buffer`].
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Contents of an embedded file, including its padding.
+    pub fn file_data(&self, file: &FWFile) -> &[u8] {
+        self.file_slice(file, ExtractMode::Padded)
+    }
+
+    /// Contents of an embedded file, as written by [`UnidenFirmware::extract_to`].
+    pub fn file_slice(&self, file: &FWFile, mode: ExtractMode) -> &[u8] {
+        let (start, end) = self.file_range(file, mode);
+        &self.buffer[start..end]
+    }
+
+    fn file_range(&self, file: &FWFile, mode: ExtractMode) -> (usize, usize) {
+        let info = file.info.base();
+        let start = info.offset as usize;
+        let length = match mode {
+            ExtractMode::Padded => info.length as usize,
+            ExtractMode::Trimmed => info.length.min(info.declared_length).max(0) as usize,
+            ExtractMode::Raw => info.length as usize + trailer_length(file.kind.to_name()),
+        };
+        (start, (start + length).min(self.buffer.len()))
     }
 
     fn update_unread_intervals(&mut self) {
@@ -197,351 +686,169 @@ impl UnidenFirmware {
         self.update_unread_intervals();
     }
 
+    /// Parse the BLOB. In lenient mode, sections that can't be read are recorded
+    /// as diagnostics, kept as damaged components where possible, and parsing
+    /// carries on from the next recognizable tag or terminator.
     pub fn read_buffer(&mut self) -> io::Result<()> {
-        let mut files = Vec::new();
-        let mut metadata = FWMetadata {
-            model: RDModel::Unknown,
-            format_version: 0,
-            new_merge_file: false,
+        let mut sections = Sections::new(&self.buffer).verify_unknown_tags(self.lenient);
+        let generation = sections.generation();
+        let format_version = sections.format_version();
+
+        let mut state = ReadState {
+            files: Vec::new(),
+            metadata: FWMetadata {
+                model: RDModel::Unknown,
+                generation,
+                format_version,
+            },
+            diagnostics: Vec::new(),
         };
 
-        let mut cursor: TrackingCursor =
-            TrackingCursor::new(&self.buffer, &mut self.read_intervals);
-
-        let first_element = i32::from_le_bytes(cursor.read_n(4)?.try_into().unwrap());
-
-        let ui_nu_len = alter_length(first_element & 0xFFFFFF);
-        let flag_includes_sound_db = (first_element >> 0x18) & 0x1;
-
-        let dsp_nu_len = alter_length(i32::from_le_bytes(cursor.read_n(4)?.try_into().unwrap()));
-        let gps_nu_len = alter_length(i32::from_le_bytes(cursor.read_n(4)?.try_into().unwrap()));
-
-        let mut sound_db_nu_len = 0;
-        if flag_includes_sound_db == 1 {
-            let slice = &cursor.read_n(12)?[8..];
-            sound_db_nu_len = i32::from_le_bytes(slice.try_into().unwrap());
-        }
-
-        if ui_nu_len != 0 {
-            let ui_nu_offset = cursor.position();
-            cursor.seek(ui_nu_len as u64);
-
-            let arr = cursor.read_n(9)?;
-            let mv_data = i16::from_le_bytes(arr[0..2].try_into().unwrap());
-
-            let model = RDModel::from_data(mv_data);
-            let ui_nu_version = rd_version(mv_data);
-
-            if String::from_utf8(arr[2..].to_vec()).unwrap() != "DRSWMAI" {
-                // todo: replace these panics with a custom error
-                panic!("Wrong format.");
-            }
-
-            metadata.model = model;
-            let info = FileInfo::Base(FileInfoBase {
-                length: ui_nu_len,
-                offset: ui_nu_offset as i32,
-                version: ui_nu_version as i32,
-            });
-            files.push(FWFile {
-                kind: FWFileKind::UiNu(info),
-                info,
-            });
-        }
-
-        if dsp_nu_len != 0 {
-            let (offset, version, endstr) = parse_file_basic(&mut cursor, dsp_nu_len)?;
-            if endstr != "DRSWDSP" {
-                panic!("Wrong format.");
-            }
-
-            let info = FileInfo::Base(FileInfoBase {
-                length: dsp_nu_len,
-                offset,
-                version,
-            });
-            files.push(FWFile {
-                kind: FWFileKind::DspNu(info),
-                info,
-            });
-        }
-
-        if gps_nu_len != 0 {
-            let (offset, version, endstr) = parse_file_basic(&mut cursor, gps_nu_len)?;
-            if endstr != "DRSWSUB" {
-                panic!("Wrong format.");
-            }
-
-            let info = FileInfo::Base(FileInfoBase {
-                length: dsp_nu_len,
-                offset,
-                version,
-            });
-            files.push(FWFile {
-                kind: FWFileKind::GpsNu(info),
-                info,
-            });
-        }
-
-        if sound_db_nu_len != 0 {
-            let offset = cursor.position() as i32;
-            cursor.seek(sound_db_nu_len as u64 - 12);
-
-            let arr = cursor.read_n(12)?;
-            let vbuf = decode_old_model(SOUND_DB_KEY, &arr, 0, 4);
-
-            let version = rd_version(i32::from_le_bytes(vbuf.try_into().unwrap()) as i16) as i32;
-
-            let arr = cursor.read_n(7)?;
-            if String::from_utf8(arr).unwrap() != "DRSWSDB" {
-                panic!("Wrong format.");
-            }
-
-            let info = FileInfo::Base(FileInfoBase {
-                length: sound_db_nu_len,
-                offset,
-                version,
-            });
-            files.push(FWFile {
-                kind: FWFileKind::SoundDbnu(info),
-                info,
-            });
-        }
-
-        if cursor.position() == self.size {
-            return Ok(());
-        }
-
-        while cursor.position() != self.size {
-            let arr = cursor.read_n(12)?;
-            let switch = String::from_utf8(arr[0..4].to_vec()).unwrap();
-            let current_length = i32::from_le_bytes(arr[8..].try_into().unwrap());
-            let current_offset = cursor.position();
-
-            match switch.as_ref() {
-                "GPSD" | "GASD" => {
-                    cursor.seek(current_length as u64 - 12);
-                    let arr = cursor.read_n(12)?;
-                    let gps_db = stfu!(arr[8..]);
-                    let mut file = GpsDbFileInfo {
-                        info: FileInfoBase {
-                            length: current_length,
-                            offset: current_offset as i32,
-                            version: 0,
-                        },
-                        poi: 0,
-                        file_type: GpsDbType::Unknown,
-                        country: None,
-                    };
-                    if OLD_FILE_GPS_DB_IDENTIFY_STR.contains(&&*gps_db) {
-                        file.file_type = GpsDbType::GpsDbOldEnc;
-                        let (key, country) = match gps_db.as_ref() {
-                            "LRDB" => (OLD_US_GPS_DB_KEY, GpsDbCountry::Us),
-                            "DFDB" => (OLD_NZ_GPS_DB_KEY, GpsDbCountry::Nz),
-                            "IRDB" => (OLD_IL_GPS_DB_KEY, GpsDbCountry::Il),
-                            _ => unreachable!(),
-                        };
-                        file.country = Some(country);
-                        file.poi = i32::from_le_bytes(
-                            decode_old_model(key, &arr, 0, 4).try_into().unwrap(),
-                        );
-                    } else if NEW_FILE_GPS_DB_IDENTIFY_STR.contains(&&*gps_db) {
-                        file.file_type = GpsDbType::GpsDbAes128;
-                        let country = match gps_db.as_ref() {
-                            "AEUS" => GpsDbCountry::Us,
-                            "AENZ" => GpsDbCountry::Nz,
-                            "AEIL" => GpsDbCountry::Il,
-                            "AEEU" => GpsDbCountry::Eu,
-                            _ => unreachable!(),
-                        };
-                        file.country = Some(country);
-                        file.poi = i32::from_le_bytes(arr[0..4].try_into().unwrap());
-                    } else {
-                        panic!("Malformed GPS DB File Info!");
-                    }
-
-                    file.info.version = i32::from_le_bytes(arr[4..8].try_into().unwrap());
-
-                    if switch == "GASD" {
-                        cursor.seek(2);
-                    }
-
-                    let term_string = stfu!(cursor.read_n(7)?);
-                    if (term_string != "DRSWGDB" && switch == "GPSD")
-                        || (term_string != "DRSWGAE" && switch == "GASD")
-                    {
-                        panic!("Error found on termination string.");
-                    }
+        let read_intervals = &mut self.read_intervals;
+        let mut on_read = |start: usize, stop: usize| {
+            read_intervals.push(Iv {
+                start: start as u64,
+                stop: stop as u64,
+                val: (),
+            })
+        };
 
-                    files.push(match switch.as_ref() {
-                        "GPSD" => FWFile {
-                            kind: FWFileKind::GpsDb(file),
-                            info: FileInfo::GpsDb(file),
-                        },
-                        "GASD" => FWFile {
-                            kind: FWFileKind::GpsDbSecond(file),
-                            info: FileInfo::GpsDb(file),
-                        },
-                        _ => unreachable!(),
-                    });
+        while let Some(result) = sections.next_with(&mut on_read) {
+            let e = match result {
+                Ok(section) => {
+                    state.push(&section);
+                    continue;
                 }
-                "BLES" | "KEYS" | "LSRS" | "STUI" | "STDS" | "STGP" | "N2UI" | "N2DS" | "N3DS"
-                | "N2GP" | "N3GP" => {
-                    let length_modifier = if switch == "BLES" { 1024 } else { 512 };
-                    let length = (current_length / length_modifier + 1) * length_modifier;
-                    let expected_termstr = format!("DRSW{}", &switch[0..3]);
-
-                    let (offset, version, term_str) = parse_file_basic(&mut cursor, length)?;
-
-                    if expected_termstr != term_str {
-                        panic!("Wrong termination sequence: {}", switch)
-                    }
-
-                    let file = FileInfoBase {
-                        length,
+                Err(e) if !self.lenient => return Err(io_error(e)),
+                Err(e) => e,
+            };
+
+            match e {
+                ufwt_core::Error::WrongTerminator {
+                    offset,
+                    name,
+                    terminator,
+                    ..
+                } => {
+                    let next = state.terminator_mismatch(
+                        &self.buffer,
+                        name,
                         offset,
-                        version,
-                    };
-
-                    files.push(match switch.as_ref() {
-                        "BLES" => FWFile {
-                            kind: FWFileKind::Ble(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "KEYS" => FWFile {
-                            kind: FWFileKind::Keypad(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "LSRS" => FWFile {
-                            kind: FWFileKind::LaserIf(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "STUI" => FWFile {
-                            kind: FWFileKind::UiStm(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "STDS" => FWFile {
-                            kind: FWFileKind::DspStm(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "STGP" => FWFile {
-                            kind: FWFileKind::GpsStm(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "N2UI" => FWFile {
-                            kind: FWFileKind::UiNu2(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "N2DS" => FWFile {
-                            kind: FWFileKind::DspNu2(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "N3DS" => FWFile {
-                            kind: FWFileKind::DspNu3(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "N2GP" => FWFile {
-                            kind: FWFileKind::GpsNu2(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "N3GP" => FWFile {
-                            kind: FWFileKind::GpsNu3(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        _ => unreachable!(),
-                    });
+                        terminator,
+                        io_error(e),
+                    );
+                    sections.seek(next);
                 }
-                "STSD" | "SUSD" => {
-                    let expected_termstr = format!("DRSW{}", &switch[0..3]);
-                    cursor.seek(current_length as u64 - 12);
-
-                    let arr = cursor.read_n(12)?;
-                    let vbuf = decode_old_model(SOUND_DB_KEY, &arr, 0, 4);
-                    let version = rd_version(i32::from_le_bytes(vbuf.try_into().unwrap()) as i16);
-
-                    if switch == "SUSD" {
-                        cursor.seek(2);
-                    }
-
-                    let termstr = stfu!(cursor.read_n(7)?);
-                    if expected_termstr != termstr {
-                        panic!("Wrong termination sequence: {}", switch)
-                    }
-
-                    let file = FileInfoBase {
-                        length: current_length,
-                        offset: current_offset as i32,
-                        version: version as i32,
-                    };
-
-                    files.push(match switch.as_ref() {
-                        "STSD" => FWFile {
-                            kind: FWFileKind::SoundDbla1(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        "SUSD" => FWFile {
-                            kind: FWFileKind::SoundDbla2(FileInfo::Base(file)),
-                            info: FileInfo::Base(file),
-                        },
-                        _ => unreachable!(),
+                ufwt_core::Error::MisplacedMergeRecord { offset }
+                | ufwt_core::Error::MergeRecordOverlap { offset } => {
+                    // the record ending merged BLOBs is read separately
+                    state.diagnostics.push(Diagnostic {
+                        offset: offset as u64,
+                        message: e.to_string(),
                     });
-                }
-                "NMGF" => {
-                    if cursor.position() == self.size {
-                        metadata.new_merge_file = true;
-                        metadata.format_version = i32::from_le_bytes(arr[8..12].try_into().unwrap())
-                    }
+                    sections.seek(sections.position());
                 }
                 _ => {
-                    if switch[2..4].to_string() == "SD" {
-                        cursor.seek(current_length as u64 + 9);
-                    } else {
-                        cursor.seek(alter_length(current_length) as u64 + 9);
-                    }
+                    let pending = sections
+                        .pending()
+                        .map(|(name, offset)| (name, offset as u64));
+                    let next = recover(
+                        &self.buffer,
+                        e.offset() as u64,
+                        pending,
+                        io_error(e),
+                        &mut state.files,
+                        &mut state.diagnostics,
+                    );
+                    sections.skip_header();
+                    sections.seek(next as usize);
                 }
             }
         }
 
+        let ReadState {
+            files,
+            mut metadata,
+            diagnostics,
+            ..
+        } = state;
+        if generation == FormatGeneration::GpsDbPackage {
+            let country = files
+                .iter()
+                .find_map(|file| handle_gpsdb_file_info(&file.kind).and_then(|info| info.country));
+            if let Some(country) = country {
+                metadata.model = country.db_model();
+            }
+        }
+
         self.files = files;
         self.metadata = Some(metadata);
+        self.diagnostics = diagnostics;
         self.update_intervals();
 
         Ok(())
     }
 
-    pub fn extract_to(&mut self, directory: &path::Path) {
+    pub fn extract_to(&mut self, directory: &path::Path, mode: ExtractMode) {
+        let ranges: Vec<_> = self
+            .files
+            .iter()
+            .map(|file| (file.kind.to_file_name(), self.file_range(file, mode)))
+            .collect();
         let mut cursor: TrackingCursor =
             TrackingCursor::new(&self.buffer, &mut self.read_intervals);
-        for file in &self.files {
+        for (name, (start, end)) in ranges {
             let mut fpath = path::PathBuf::from(directory);
-            fpath.push(file.kind.to_file_name());
+            fpath.push(name);
             let mut f = fs::File::create(&fpath)
                 .unwrap_or_else(|_| panic!("Couldn't create output file: {}", fpath.display()));
-            if let FileInfo::Base(fib) = file.info {
-                cursor.seek_set(fib.offset as u64);
-                f.write_all(
-                    cursor
-                        .read_n(fib.length as usize)
-                        .unwrap_or_else(|_| {
-                            panic!("Couldn't read firmware inner file: {}", fpath.display())
-                        })
-                        .as_ref(),
-                )
-                .unwrap_or_else(|_| panic!("Couldn't write output file: {}", fpath.display()));
-            } else if let FileInfo::GpsDb(fibgps) = file.info {
-                cursor.seek_set(fibgps.info.offset as u64);
-                f.write_all(
-                    cursor
-                        .read_n(fibgps.info.length as usize)
-                        .unwrap_or_else(|_| {
-                            panic!("Couldn't read firmware inner file: {}", fpath.display())
-                        })
-                        .as_ref(),
-                )
-                .unwrap_or_else(|_| panic!("Couldn't write output file: {}", fpath.display()));
-            }
+            cursor.seek_set(start as u64);
+            f.write_all(
+                cursor
+                    .read_n(end - start)
+                    .unwrap_or_else(|_| {
+                        panic!("Couldn't read firmware inner file: {}", fpath.display())
+                    })
+                    .as_ref(),
+            )
+            .unwrap_or_else(|_| panic!("Couldn't write output file: {}", fpath.display()));
         }
         self.update_intervals();
+        #[cfg(feature = "serde")]
+        self.write_manifest(directory, mode);
+    }
+
+    #[cfg(feature = "serde")]
+    fn write_manifest(&self, directory: &path::Path, mode: ExtractMode) {
+        let metadata = self.metadata.as_ref().unwrap();
+        let manifest = Manifest {
+            generation: metadata.generation.to_name(),
+            format_version: metadata.format_version,
+            model: metadata.model.to_name(),
+            mode: mode.to_name(),
+            components: self
+                .files
+                .iter()
+                .map(|file| {
+                    let info = file.info.base();
+                    ManifestComponent {
+                        name: file.kind.to_name(),
+                        file: file.kind.to_file_name(),
+                        offset: info.offset,
+                        length: info.length,
+                        declared_length: info.declared_length,
+                        version: info.version,
+                        damaged: file.damaged,
+                        trailer: file.trailer.as_ref().map(ManifestTrailer::new),
+                    }
+                })
+                .collect(),
+        };
+
+        let fpath = directory.join("manifest.json");
+        let json = serde_json::to_string_pretty(&manifest).unwrap();
+        fs::write(&fpath, json)
+            .unwrap_or_else(|_| panic!("Couldn't write manifest: {}", fpath.display()));
     }
 
     pub fn print_intervals(&self) {
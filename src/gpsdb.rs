@@ -0,0 +1,612 @@
+use crate::file::{db_terminator, UnidenFirmware};
+use crate::format::{decode_old_model, encode_old_model, GpsDbCountry};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+#[cfg(feature = "serde")]
+use std::{fs, path::Path};
+
+/// POI count (4) + version (4) + identify string (4), as read when parsing a BLOB
+pub const GPS_DB_TRAILER_LEN: usize = 12;
+
+/// Size of a decoded POI record:
+/// latitude (4) + longitude (4) + type (1) + speed (1) + heading (2) + reserved (4)
+///
+/// Unlike the trailer, this layout is assumed and hasn't been checked against
+/// a DB shipped by Uniden, see [`GpsDb`].
+pub const RECORD_LEN: usize = 16;
+
+/// Coordinates are stored in millionths of a degree.
+const COORD_SCALE: f64 = 1_000_000.0;
+
+/// Heading value of cameras that trigger in every direction.
+const ANY_HEADING: u16 = 0xFFFF;
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A GPS DB not following the assumed POI record layout.
+fn unexpected_layout(msg: String) -> io::Error {
+    invalid_data(format!(
+        "{}, the GPS DB does not follow the assumed POI record layout",
+        msg
+    ))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CameraType {
+    RedLight,
+    Speed,
+    RedLightSpeed,
+    AverageSpeed,
+    Mobile,
+    Other(u8),
+}
+
+impl From<u8> for CameraType {
+    fn from(item: u8) -> Self {
+        match item {
+            0 => CameraType::RedLight,
+            1 => CameraType::Speed,
+            2 => CameraType::RedLightSpeed,
+            3 => CameraType::AverageSpeed,
+            4 => CameraType::Mobile,
+            other => CameraType::Other(other),
+        }
+    }
+}
+
+impl From<CameraType> for u8 {
+    fn from(item: CameraType) -> Self {
+        match item {
+            CameraType::RedLight => 0,
+            CameraType::Speed => 1,
+            CameraType::RedLightSpeed => 2,
+            CameraType::AverageSpeed => 3,
+            CameraType::Mobile => 4,
+            CameraType::Other(other) => other,
+        }
+    }
+}
+
+impl CameraType {
+    /// Accepts the names returned by [`CameraType::to_name`] or a raw type number.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase().replace(['-', ' '], "_");
+        match name.as_ref() {
+            "red_light" => Some(CameraType::RedLight),
+            "speed" => Some(CameraType::Speed),
+            "red_light_speed" => Some(CameraType::RedLightSpeed),
+            "average_speed" => Some(CameraType::AverageSpeed),
+            "mobile" => Some(CameraType::Mobile),
+            _ => name
+                .strip_prefix("type_")
+                .unwrap_or(&name)
+                .parse::<u8>()
+                .ok()
+                .map(CameraType::from),
+        }
+    }
+
+    pub fn to_name(&self) -> String {
+        match self {
+            CameraType::RedLight => "red_light".into(),
+            CameraType::Speed => "speed".into(),
+            CameraType::RedLightSpeed => "red_light_speed".into(),
+            CameraType::AverageSpeed => "average_speed".into(),
+            CameraType::Mobile => "mobile".into(),
+            CameraType::Other(other) => format!("type_{}", other),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for CameraType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for CameraType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        CameraType::from_name(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown camera type: {}", name)))
+    }
+}
+
+/// A single point of interest (camera) of a GPS DB.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Poi {
+    pub latitude: f64,
+    pub longitude: f64,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub camera: CameraType,
+    /// Speed limit in the unit used by the target country
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub speed: u8,
+    /// Heading in degrees, `None` for cameras that trigger in every direction
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub heading: Option<u16>,
+}
+
+impl Poi {
+    fn validate(&self) -> io::Result<()> {
+        if !(-90.0..=90.0).contains(&self.latitude) || !(-180.0..=180.0).contains(&self.longitude) {
+            return Err(invalid_data(format!(
+                "Coordinates out of range: {}, {}",
+                self.latitude, self.longitude
+            )));
+        }
+        if self.heading.is_some_and(|heading| heading >= 360) {
+            return Err(invalid_data(format!(
+                "Heading out of range: {}",
+                self.heading.unwrap()
+            )));
+        }
+        Ok(())
+    }
+
+    fn from_record(record: &[u8]) -> Poi {
+        let latitude = i32::from_le_bytes(record[0..4].try_into().unwrap());
+        let longitude = i32::from_le_bytes(record[4..8].try_into().unwrap());
+        let heading = u16::from_le_bytes(record[10..12].try_into().unwrap());
+        Poi {
+            latitude: latitude as f64 / COORD_SCALE,
+            longitude: longitude as f64 / COORD_SCALE,
+            camera: record[8].into(),
+            speed: record[9],
+            heading: (heading != ANY_HEADING).then_some(heading),
+        }
+    }
+
+    fn to_record(&self) -> [u8; RECORD_LEN] {
+        let mut record = [0u8; RECORD_LEN];
+        let latitude = (self.latitude * COORD_SCALE).round() as i32;
+        let longitude = (self.longitude * COORD_SCALE).round() as i32;
+        record[0..4].copy_from_slice(&latitude.to_le_bytes());
+        record[4..8].copy_from_slice(&longitude.to_le_bytes());
+        record[8] = self.camera.into();
+        record[9] = self.speed;
+        record[10..12].copy_from_slice(&self.heading.unwrap_or(ANY_HEADING).to_le_bytes());
+        record
+    }
+}
+
+/// An old encoding GPS DB (`LRDB`, `DFDB` or `IRDB`).
+///
+/// The POI records and the count in the trailer are encoded with the key of the
+/// country, while the version and identify string are stored as-is.
+///
+/// Only the trailer is known from firmware BLOBs. The body is assumed to hold
+/// nothing but one [`RECORD_LEN`] byte record per POI, so [`GpsDb::parse`]
+/// rejects any DB not following that layout exactly rather than guessing.
+pub struct GpsDb {
+    pub country: GpsDbCountry,
+    pub version: i32,
+    pub pois: Vec<Poi>,
+}
+
+impl GpsDb {
+    /// Parse a GPS DB either bare, as accepted by [`GpsDb::parse`], or as the
+    /// `gps_db` section of a standalone GPS DB package, whatever the order of
+    /// its `GPSD` and `GASD` sections.
+    pub fn parse_file(data: &[u8]) -> io::Result<GpsDb> {
+        if !matches!(data.get(0..4), Some(b"GPSD") | Some(b"GASD")) {
+            return GpsDb::parse(data);
+        }
+        let mut firmware = UnidenFirmware::from_bytes(data);
+        firmware.read_buffer()?;
+        let file = firmware
+            .find_file("gps_db")
+            .ok_or_else(|| invalid_data("GPS DB package without a GPSD section".into()))?;
+        GpsDb::parse(firmware.file_data(file))
+    }
+
+    /// Parse a GPS DB as extracted from a BLOB, trailer included. A trailing
+    /// `DRSWGDB` terminator, as written by [`GpsDb::build_section`], is ignored.
+    pub fn parse(data: &[u8]) -> io::Result<GpsDb> {
+        let (_, terminator) = db_terminator("gps_db").unwrap();
+        let data = data.strip_suffix(terminator.as_bytes()).unwrap_or(data);
+        if data.len() < GPS_DB_TRAILER_LEN {
+            return Err(invalid_data("GPS DB is too short".into()));
+        }
+
+        let trailer = &data[data.len() - GPS_DB_TRAILER_LEN..];
+        let identify = String::from_utf8_lossy(&trailer[8..]);
+        let country = match identify.as_ref() {
+            "LRDB" => GpsDbCountry::Us,
+            "DFDB" => GpsDbCountry::Nz,
+            "IRDB" => GpsDbCountry::Il,
+            _ => {
+                return Err(invalid_data(format!(
+                    "Unsupported GPS DB encoding: {}",
+                    identify
+                )))
+            }
+        };
+        let (key, _) = country.old_enc_key().unwrap();
+
+        let count = u32::from_le_bytes(decode_old_model(key, trailer, 0, 4).try_into().unwrap());
+        let version = i32::from_le_bytes(trailer[4..8].try_into().unwrap());
+
+        let body_len = data.len() - GPS_DB_TRAILER_LEN;
+        if (count as usize).checked_mul(RECORD_LEN) != Some(body_len) {
+            return Err(unexpected_layout(format!(
+                "{} POIs do not make up {} bytes",
+                count, body_len
+            )));
+        }
+
+        let records = decode_old_model(key, data, 0, body_len);
+        let pois = records
+            .chunks_exact(RECORD_LEN)
+            .enumerate()
+            .map(|(index, record)| {
+                let poi = Poi::from_record(record);
+                if record[12..] != [0; 4] || poi.validate().is_err() {
+                    return Err(unexpected_layout(format!("Invalid POI {}", index)));
+                }
+                Ok(poi)
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(GpsDb {
+            country,
+            version,
+            pois,
+        })
+    }
+
+    /// Encode the database as stored in a BLOB, trailer included.
+    pub fn build(&self) -> io::Result<Vec<u8>> {
+        let (key, identify) = self.country.old_enc_key().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "There is no old encoding GPS DB for {}",
+                    self.country.to_name()
+                ),
+            )
+        })?;
+
+        let mut records = Vec::with_capacity(self.pois.len() * RECORD_LEN);
+        for poi in &self.pois {
+            poi.validate()?;
+            records.extend_from_slice(&poi.to_record());
+        }
+
+        let mut out = encode_old_model(key, &records, 0, records.len());
+        let count = (self.pois.len() as u32).to_le_bytes();
+        out.extend(encode_old_model(key, &count, 0, 4));
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(identify.as_bytes());
+        Ok(out)
+    }
+
+    /// Encode the database followed by its `DRSWGDB` terminator, ready to be
+    /// inserted into a BLOB.
+    pub fn build_section(&self) -> io::Result<Vec<u8>> {
+        let (_, terminator) = db_terminator("gps_db").unwrap();
+        let mut out = self.build()?;
+        out.extend_from_slice(terminator.as_bytes());
+        Ok(out)
+    }
+}
+
+/// Read POIs from a CSV, GPX or GeoJSON file, picked by its extension.
+#[cfg(feature = "serde")]
+pub fn read_pois(path: &Path) -> io::Result<Vec<Poi>> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let data = fs::read_to_string(path)?;
+
+    let pois = match extension.as_ref() {
+        "csv" => pois_from_csv(&data)?,
+        "gpx" => pois_from_gpx(&data)?,
+        "geojson" | "json" => pois_from_geojson(&data)?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown POI file format: {}", path.display()),
+            ))
+        }
+    };
+    for poi in &pois {
+        poi.validate()?;
+    }
+    Ok(pois)
+}
+
+/// CSV with a header naming the `latitude`, `longitude`, `type`, `speed` and
+/// `heading` columns, the last two being optional.
+#[cfg(feature = "serde")]
+fn pois_from_csv(data: &str) -> io::Result<Vec<Poi>> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data.as_bytes())
+        .deserialize()
+        .collect::<Result<Vec<Poi>, _>>()
+        .map_err(|e| invalid_data(e.to_string()))
+}
+
+/// GPX waypoints, using `<type>` for the camera type and the `<speed>` and
+/// `<course>` elements (GPX 1.0 or inside `<extensions>`) when present.
+#[cfg(feature = "serde")]
+fn pois_from_gpx(data: &str) -> io::Result<Vec<Poi>> {
+    let document = roxmltree::Document::parse(data).map_err(|e| invalid_data(e.to_string()))?;
+
+    let mut pois = vec![];
+    for wpt in document
+        .descendants()
+        .filter(|node| node.has_tag_name("wpt"))
+    {
+        let coordinate = |name: &str| {
+            wpt.attribute(name)
+                .and_then(|value| value.parse::<f64>().ok())
+                .ok_or_else(|| invalid_data(format!("Waypoint without a valid {}", name)))
+        };
+        let element = |name: &str| {
+            wpt.descendants()
+                .find(|node| node.has_tag_name(name))
+                .and_then(|node| node.text())
+                .map(str::trim)
+        };
+
+        let camera = element("type").unwrap_or("speed");
+        let camera = CameraType::from_name(camera)
+            .ok_or_else(|| invalid_data(format!("Unknown camera type: {}", camera)))?;
+        let number = |name: &str| -> io::Result<Option<f64>> {
+            element(name)
+                .map(|value| {
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| invalid_data(format!("Invalid {}: {}", name, value)))
+                })
+                .transpose()
+        };
+
+        let speed = number("speed")?.unwrap_or(0.0).round();
+        if !(0.0..=u8::MAX as f64).contains(&speed) {
+            return Err(invalid_data(format!("Speed out of range: {}", speed)));
+        }
+        let heading = number("course")?.map(f64::round);
+        if let Some(heading) = heading.filter(|heading| !(0.0..=u16::MAX as f64).contains(heading))
+        {
+            return Err(invalid_data(format!("Heading out of range: {}", heading)));
+        }
+
+        pois.push(Poi {
+            latitude: coordinate("lat")?,
+            longitude: coordinate("lon")?,
+            camera,
+            speed: speed as u8,
+            heading: heading.map(|heading| heading as u16),
+        });
+    }
+    Ok(pois)
+}
+
+/// GeoJSON `Point` features, with `type`, `speed` and `heading` properties.
+#[cfg(feature = "serde")]
+fn pois_from_geojson(data: &str) -> io::Result<Vec<Poi>> {
+    #[derive(Deserialize)]
+    struct Geometry {
+        coordinates: Vec<f64>,
+    }
+    #[derive(Deserialize)]
+    struct Properties {
+        #[serde(rename = "type")]
+        camera: CameraType,
+        #[serde(default)]
+        speed: u8,
+        #[serde(default)]
+        heading: Option<u16>,
+    }
+    #[derive(Deserialize)]
+    struct Feature {
+        geometry: Geometry,
+        properties: Properties,
+    }
+    #[derive(Deserialize)]
+    struct FeatureCollection {
+        features: Vec<Feature>,
+    }
+
+    let collection: FeatureCollection =
+        serde_json::from_str(data).map_err(|e| invalid_data(e.to_string()))?;
+    collection
+        .features
+        .into_iter()
+        .map(|feature| match feature.geometry.coordinates[..] {
+            [longitude, latitude, ..] => Ok(Poi {
+                latitude,
+                longitude,
+                camera: feature.properties.camera,
+                speed: feature.properties.speed,
+                heading: feature.properties.heading,
+            }),
+            _ => Err(invalid_data("Point without coordinates".into())),
+        })
+        .collect()
+}
+
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+const METERS_PER_DEGREE: f64 = EARTH_RADIUS_M * std::f64::consts::PI / 180.0;
+
+/// Great-circle distance between two POIs in meters.
+pub fn distance(a: &Poi, b: &Poi) -> f64 {
+    let (lat1, lat2) = (a.latitude.to_radians(), b.latitude.to_radians());
+    let dlat = lat2 - lat1;
+    // the short way round, across the antimeridian if need be
+    let dlon = ((b.longitude - a.longitude + 540.0).rem_euclid(360.0) - 180.0).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().asin()
+}
+
+/// Changes between two versions of a GPS DB.
+pub struct GpsDbDiff {
+    pub added: Vec<Poi>,
+    pub removed: Vec<Poi>,
+    /// (old, new) pairs matched by position whose type, speed or heading differ
+    pub modified: Vec<(Poi, Poi)>,
+    pub unchanged: usize,
+}
+
+/// Per camera type totals of a [`GpsDbDiff`].
+#[derive(Default)]
+pub struct DiffTotals {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+}
+
+impl GpsDbDiff {
+    /// Match the POIs of `old` and `new` one to one, closest pairs first, as long
+    /// as they are at most `tolerance` meters apart.
+    pub fn new(old: &[Poi], new: &[Poi], tolerance: f64) -> GpsDbDiff {
+        let cell = (tolerance / METERS_PER_DEGREE).max(1e-9);
+        let key = |latitude: f64, longitude: f64| {
+            (
+                (latitude / cell).floor() as i64,
+                (longitude / cell).floor() as i64,
+            )
+        };
+
+        let mut grid = HashMap::<(i64, i64), Vec<usize>>::new();
+        for (j, poi) in new.iter().enumerate() {
+            grid.entry(key(poi.latitude, poi.longitude))
+                .or_default()
+                .push(j);
+        }
+
+        let mut candidates = vec![];
+        for (i, poi) in old.iter().enumerate() {
+            // a degree of longitude shrinks towards the poles
+            let cos = poi.latitude.to_radians().cos().max(1e-3);
+            let span = (1.0 / cos).ceil().min(360.0 / cell) as i64;
+            // cells near the antimeridian neighbour those on the other side, a
+            // pair found twice near the poles being matched only once below
+            for shift in [0.0, -360.0, 360.0] {
+                let (row, col) = key(poi.latitude, poi.longitude + shift);
+                if (col - span) as f64 * cell > 180.0 || ((col + span + 1) as f64 * cell) < -180.0 {
+                    continue;
+                }
+                for r in row - 1..=row + 1 {
+                    for c in col - span..=col + span {
+                        for &j in grid.get(&(r, c)).into_iter().flatten() {
+                            let d = distance(poi, &new[j]);
+                            if d <= tolerance {
+                                candidates.push((d, i, j));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut old_matched = vec![false; old.len()];
+        let mut new_matched = vec![false; new.len()];
+        let mut modified = vec![];
+        let mut unchanged = 0;
+        for (_, i, j) in candidates {
+            if old_matched[i] || new_matched[j] {
+                continue;
+            }
+            old_matched[i] = true;
+            new_matched[j] = true;
+
+            let (a, b) = (&old[i], &new[j]);
+            if a.camera != b.camera || a.speed != b.speed || a.heading != b.heading {
+                modified.push((a.clone(), b.clone()));
+            } else {
+                unchanged += 1;
+            }
+        }
+
+        let unmatched = |pois: &[Poi], matched: &[bool]| {
+            pois.iter()
+                .zip(matched)
+                .filter(|(_, matched)| !**matched)
+                .map(|(poi, _)| poi.clone())
+                .collect::<Vec<_>>()
+        };
+
+        GpsDbDiff {
+            added: unmatched(new, &new_matched),
+            removed: unmatched(old, &old_matched),
+            modified,
+            unchanged,
+        }
+    }
+
+    /// Totals per camera type, modified cameras being counted under their new type.
+    pub fn totals(&self) -> BTreeMap<CameraType, DiffTotals> {
+        let mut totals = BTreeMap::<CameraType, DiffTotals>::new();
+        for poi in &self.added {
+            totals.entry(poi.camera).or_default().added += 1;
+        }
+        for poi in &self.removed {
+            totals.entry(poi.camera).or_default().removed += 1;
+        }
+        for (_, poi) in &self.modified {
+            totals.entry(poi.camera).or_default().modified += 1;
+        }
+        totals
+    }
+
+    /// The delta as a GeoJSON `FeatureCollection`, each feature carrying a
+    /// `change` property and, for modified cameras, the `previous` values.
+    #[cfg(feature = "serde")]
+    pub fn to_geojson(&self) -> serde_json::Value {
+        let feature = |poi: &Poi, change: &str, previous: Option<&Poi>| {
+            let mut properties = serde_json::json!({
+                "change": change,
+                "type": poi.camera,
+                "speed": poi.speed,
+                "heading": poi.heading,
+            });
+            if let Some(previous) = previous {
+                properties["previous"] = serde_json::json!({
+                    "latitude": previous.latitude,
+                    "longitude": previous.longitude,
+                    "type": previous.camera,
+                    "speed": previous.speed,
+                    "heading": previous.heading,
+                });
+            }
+            serde_json::json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [poi.longitude, poi.latitude],
+                },
+                "properties": properties,
+            })
+        };
+
+        let features = self
+            .added
+            .iter()
+            .map(|poi| feature(poi, "added", None))
+            .chain(self.removed.iter().map(|poi| feature(poi, "removed", None)))
+            .chain(
+                self.modified
+                    .iter()
+                    .map(|(old, new)| feature(new, "modified", Some(old))),
+            )
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "type": "FeatureCollection",
+            "features": features,
+        })
+    }
+}
@@ -0,0 +1,193 @@
+#[cfg(feature = "serde")]
+use crate::file::FWFileKind;
+use crate::file::{ExtractMode, UnidenFirmware};
+use crate::format::RDModel;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+#[cfg(feature = "serde")]
+use std::{io, path::Path, str::FromStr};
+
+/// Releases shipped with the tool, in the format read by [`KnownHashes::load`].
+#[cfg(feature = "serde")]
+const BUNDLED: &str = include_str!("../data/known_hashes.json");
+
+#[cfg(feature = "serde")]
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Checksums of a component image.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ComponentHashes {
+    pub crc32: u32,
+    pub sha1: [u8; 20],
+    pub sha256: [u8; 32],
+}
+
+impl ComponentHashes {
+    pub fn of(data: &[u8]) -> ComponentHashes {
+        ComponentHashes {
+            crc32: crc32fast::hash(data),
+            sha1: Sha1::digest(data).into(),
+            sha256: Sha256::digest(data).into(),
+        }
+    }
+
+    /// Hashes of every component of `firmware`, taken over the payload length
+    /// declared in the header or section tag, as written by `extract --trim`.
+    pub fn of_components(firmware: &UnidenFirmware) -> Vec<(&'static str, ComponentHashes)> {
+        firmware
+            .components()
+            .map(|component| {
+                let data = component.slice(ExtractMode::Trimmed);
+                (component.kind().to_name(), ComponentHashes::of(data))
+            })
+            .collect()
+    }
+
+    pub fn crc32_hex(&self) -> String {
+        format!("{:08x}", self.crc32)
+    }
+}
+
+/// A hash DB entry as stored in JSON. At least one hash must be given.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct Entry {
+    model: String,
+    component: String,
+    release: String,
+    #[serde(default)]
+    crc32: Option<String>,
+    #[serde(default)]
+    sha1: Option<String>,
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+/// A component image from a known release.
+#[derive(Clone)]
+pub struct KnownRelease {
+    pub model: RDModel,
+    pub component: &'static str,
+    /// Release version as published, e.g. `1.23.45`
+    pub release: String,
+    crc32: Option<u32>,
+    sha1: Option<[u8; 20]>,
+    sha256: Option<[u8; 32]>,
+}
+
+impl KnownRelease {
+    /// Whether every hash of the entry matches `hashes`.
+    pub fn matches(&self, hashes: &ComponentHashes) -> bool {
+        self.crc32.is_none_or(|crc32| crc32 == hashes.crc32)
+            && self.sha1.is_none_or(|sha1| sha1 == hashes.sha1)
+            && self.sha256.is_none_or(|sha256| sha256 == hashes.sha256)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn from_hex<const N: usize>(name: &str, hex: &str) -> io::Result<[u8; N]> {
+    let invalid = || invalid_data(format!("Invalid {} hash: {}", name, hex));
+    if hex.len() != N * 2 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut out = [0; N];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Entry> for KnownRelease {
+    type Error = io::Error;
+
+    fn try_from(entry: Entry) -> io::Result<KnownRelease> {
+        let model = RDModel::from_name(&entry.model)
+            .ok_or_else(|| invalid_data(format!("Unknown model: {}", entry.model)))?;
+        let component = FWFileKind::from_str(&entry.component)
+            .map_err(|_| invalid_data(format!("Unknown component: {}", entry.component)))?
+            .to_name();
+        if entry.crc32.is_none() && entry.sha1.is_none() && entry.sha256.is_none() {
+            return Err(invalid_data(format!(
+                "No hash given for {} {} release {}",
+                entry.model, component, entry.release
+            )));
+        }
+        Ok(KnownRelease {
+            model,
+            component,
+            release: entry.release,
+            crc32: entry
+                .crc32
+                .map(|hex| from_hex::<4>("CRC32", &hex).map(u32::from_be_bytes))
+                .transpose()?,
+            sha1: entry.sha1.map(|hex| from_hex("SHA-1", &hex)).transpose()?,
+            sha256: entry
+                .sha256
+                .map(|hex| from_hex("SHA-256", &hex))
+                .transpose()?,
+        })
+    }
+}
+
+/// Hashes of component images from known releases, to tell which release a
+/// component comes from when its trailer version is ambiguous or it was
+/// extracted out of its BLOB.
+///
+/// Entries are read from a JSON array of objects with `model`, `component`,
+/// `release` and any of `crc32`, `sha1` and `sha256` as lowercase hex, taken
+/// over the images written by `extract --trim`.
+#[derive(Default)]
+pub struct KnownHashes {
+    releases: Vec<KnownRelease>,
+}
+
+impl KnownHashes {
+    /// The releases shipped with the tool, to which hash DBs can be added.
+    #[cfg(feature = "serde")]
+    pub fn bundled() -> KnownHashes {
+        let mut known = KnownHashes::default();
+        known.add_json(BUNDLED).unwrap();
+        known
+    }
+
+    /// Add the entries of a JSON hash DB.
+    #[cfg(feature = "serde")]
+    pub fn add_json(&mut self, json: &str) -> io::Result<()> {
+        let entries: Vec<Entry> =
+            serde_json::from_str(json).map_err(|e| invalid_data(e.to_string()))?;
+        let releases = entries
+            .into_iter()
+            .map(KnownRelease::try_from)
+            .collect::<io::Result<Vec<_>>>()?;
+        self.releases.extend(releases);
+        Ok(())
+    }
+
+    /// Add the entries of the JSON hash DB at `path`.
+    #[cfg(feature = "serde")]
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        self.add_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn releases(&self) -> &[KnownRelease] {
+        &self.releases
+    }
+
+    /// Known releases holding an image with `hashes`. `component` restricts the
+    /// search to images of that component, `None` searching all of them.
+    pub fn lookup<'a>(
+        &'a self,
+        hashes: &'a ComponentHashes,
+        component: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a KnownRelease> + 'a {
+        self.releases.iter().filter(move |release| {
+            component.is_none_or(|component| component == release.component)
+                && release.matches(hashes)
+        })
+    }
+}
@@ -0,0 +1,309 @@
+use crate::file::{
+    read_gps_db_info, ExtractMode, FWFileKind, FileInfo, FileInfoBase, FormatGeneration,
+    UnidenFirmware,
+};
+use crate::format::{
+    alter_length, component_for_terminator, padding_block, GpsDbCountry, RDModel, HEADER_SECTIONS,
+    NEW_FILE_GPS_DB_IDENTIFY_STR, OLD_FILE_GPS_DB_IDENTIFY_STR, SECTION_TAGS,
+};
+use crate::sound::SoundDb;
+use crate::trailer::Trailer;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    /// Firmware BLOB ending with an `NMGF` record
+    MergedBlob,
+    /// Firmware BLOB without an `NMGF` record
+    LegacyBlob,
+    /// GPS DB update without a header, only `GPSD`/`GASD` sections
+    GpsDbPackage,
+    /// A single section, still followed by its trailer and terminator
+    Component,
+    /// A component written by `extract` from a BLOB given alongside it
+    Extracted,
+    /// A GPS DB as written by `extract`
+    GpsDb,
+    /// A sound DB as written by `extract`
+    SoundDb,
+    Unknown,
+}
+
+impl FileType {
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            FileType::MergedBlob => "merged firmware BLOB",
+            FileType::LegacyBlob => "legacy firmware BLOB",
+            FileType::GpsDbPackage => "standalone GPS DB package",
+            FileType::Component => "firmware component with trailer",
+            FileType::Extracted => "extracted firmware component",
+            FileType::GpsDb => "GPS DB",
+            FileType::SoundDb => "sound DB",
+            FileType::Unknown => "unknown",
+        }
+    }
+}
+
+/// What [`identify`] found out about a file.
+pub struct Identification {
+    pub file_type: FileType,
+    /// Component the file corresponds to, offsets being relative to the file
+    pub kind: Option<FWFileKind>,
+    pub version: Option<i32>,
+    pub model: Option<RDModel>,
+    pub country: Option<GpsDbCountry>,
+    /// `NMGF` format version of merged BLOBs
+    pub format_version: Option<i32>,
+    /// How an [`FileType::Extracted`] component was written by `extract`
+    pub mode: Option<ExtractMode>,
+    /// Length of the component image at the start of the file, as written by
+    /// `extract --trim`. Padded components identified without their BLOB keep
+    /// their padding.
+    pub trimmed_length: Option<usize>,
+    /// Embedded components of BLOBs and packages
+    pub components: Vec<&'static str>,
+}
+
+impl Identification {
+    fn new(file_type: FileType) -> Identification {
+        Identification {
+            file_type,
+            kind: None,
+            version: None,
+            model: None,
+            country: None,
+            format_version: None,
+            mode: None,
+            trimmed_length: None,
+            components: vec![],
+        }
+    }
+
+    /// The component image in `data`, which known release hashes are taken
+    /// over, or the whole of `data` if it isn't a component.
+    pub fn payload<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[..self.trimmed_length.unwrap_or(data.len()).min(data.len())]
+    }
+
+    /// Subcommands that accept this file.
+    pub fn commands(&self) -> Vec<&'static str> {
+        let has = |name: &str| self.components.iter().any(|c| c.starts_with(name));
+        let kind = self.kind.as_ref().map(|kind| kind.to_name());
+        // `replace` takes padded components without padding or trailer
+        let is_db = kind.is_some_and(|kind| padding_block(kind).is_none());
+        let mut commands = vec![];
+        match self.file_type {
+            FileType::MergedBlob | FileType::LegacyBlob | FileType::GpsDbPackage => {
+                commands.extend(["parse", "extract", "replace", "set-version"]);
+                if has("sound_db") {
+                    commands.push("sounds");
+                }
+                if self.file_type == FileType::GpsDbPackage {
+                    commands.push("gpsdb-diff");
+                } else {
+                    commands.push("import-pois --insert");
+                }
+            }
+            FileType::Component | FileType::Extracted => {
+                if is_db || self.mode == Some(ExtractMode::Trimmed) {
+                    commands.push("replace --with");
+                }
+                if kind == Some("gps_db") {
+                    commands.push("gpsdb-diff");
+                }
+            }
+            FileType::GpsDb => commands.extend(["replace --with", "gpsdb-diff"]),
+            FileType::SoundDb => commands.push("replace --with"),
+            FileType::Unknown => {}
+        }
+        commands
+    }
+}
+
+/// Work out what kind of file `data` is: a BLOB, a GPS DB package, a component
+/// with its trailer, or a GPS/sound DB as written by `extract`.
+pub fn identify(data: &[u8]) -> Identification {
+    if matches!(data.get(0..4), Some(b"GPSD") | Some(b"GASD")) {
+        return identify_firmware(data, FileType::GpsDbPackage);
+    }
+    if sniff_header(data) {
+        return identify_firmware(data, FileType::LegacyBlob);
+    }
+    if let Some(identification) = identify_component(data) {
+        return identification;
+    }
+    if let Some(identification) = identify_gps_db(data) {
+        return identification;
+    }
+    if let Ok(sound_db) = SoundDb::parse(data) {
+        let mut identification = Identification::new(FileType::SoundDb);
+        identification.version = Some(sound_db.version as i32);
+        return identification;
+    }
+    Identification::new(FileType::Unknown)
+}
+
+fn identify_firmware(data: &[u8], file_type: FileType) -> Identification {
+    let mut firmware = UnidenFirmware::from_bytes(data);
+    if firmware.read_buffer().is_err() {
+        return Identification::new(FileType::Unknown);
+    }
+
+    let mut identification = Identification::new(file_type);
+    identification.components = firmware.files.iter().map(|f| f.kind.to_name()).collect();
+    if let Some(metadata) = firmware.metadata.as_ref() {
+        identification.model = Some(metadata.model);
+        if metadata.generation == FormatGeneration::Merged {
+            identification.file_type = FileType::MergedBlob;
+            identification.format_version = metadata.format_version;
+        }
+    }
+    if file_type == FileType::GpsDbPackage {
+        identification.file_type = file_type;
+        identification.country = firmware.files.iter().find_map(|file| match file.info {
+            FileInfo::GpsDb(info) => info.country,
+            _ => None,
+        });
+    }
+    identification
+}
+
+/// Check that the lengths in the header lead to the terminators of the
+/// sections it describes, and then to a section tag or the end of the file.
+fn sniff_header(data: &[u8]) -> bool {
+    let word = |pos: usize| {
+        data.get(pos..pos + 4)
+            .map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let (Some(first), Some(dsp_nu), Some(gps_nu)) = (word(0), word(4), word(8)) else {
+        return false;
+    };
+    let includes_sound_db = (first >> 0x18) & 1 == 1;
+    let sound_db_nu = if includes_sound_db { word(20) } else { Some(0) };
+    let Some(sound_db_nu) = sound_db_nu else {
+        return false;
+    };
+
+    let lengths = [first & 0xFFFFFF, dsp_nu, gps_nu];
+    if lengths.iter().chain([&sound_db_nu]).any(|len| *len < 0) {
+        return false;
+    }
+
+    let mut pos = if includes_sound_db { 24 } else { 12 };
+    for ((_, terminator), length) in HEADER_SECTIONS.iter().zip(lengths) {
+        if length == 0 {
+            continue;
+        }
+        let Some(length) = alter_length(length) else {
+            return false;
+        };
+        pos += length as usize;
+        if data.get(pos + 2..pos + 9) != Some(terminator.as_bytes()) {
+            return false;
+        }
+        pos += 9;
+    }
+    if sound_db_nu != 0 {
+        pos += sound_db_nu as usize;
+        if data.get(pos..pos + 7) != Some(HEADER_SECTIONS[3].1.as_bytes()) {
+            return false;
+        }
+        pos += 7;
+    }
+
+    match data.get(pos..pos + 4) {
+        None => pos == data.len() && pos > 12,
+        Some(tag) => tag == b"NMGF" || SECTION_TAGS.iter().any(|(t, _, _)| t.as_bytes() == tag),
+    }
+}
+
+/// Identify `data` as a component written by `extract` from `firmware`, in any
+/// of the [`ExtractMode`]s, the declared length being preferred.
+pub fn identify_extracted(data: &[u8], firmware: &UnidenFirmware) -> Option<Identification> {
+    if data.is_empty() {
+        return None;
+    }
+    let modes = [ExtractMode::Trimmed, ExtractMode::Padded, ExtractMode::Raw];
+    let (file, mode) = modes.into_iter().find_map(|mode| {
+        firmware
+            .files
+            .iter()
+            .find(|file| firmware.file_slice(file, mode) == data)
+            .map(|file| (file, mode))
+    })?;
+
+    let mut identification = Identification::new(FileType::Extracted);
+    identification.kind = FWFileKind::from_name(file.kind.to_name(), file.info);
+    identification.mode = Some(mode);
+    identification.trimmed_length = Some(firmware.file_slice(file, ExtractMode::Trimmed).len());
+    identification.version = Some(file.info.base().version);
+    identification.model = file
+        .trailer
+        .as_ref()
+        .and_then(|trailer| trailer.model())
+        .or(firmware.metadata.as_ref().map(|metadata| metadata.model));
+    identification.country = match file.info {
+        FileInfo::GpsDb(info) => info.country,
+        FileInfo::Base(_) => None,
+    };
+    Some(identification)
+}
+
+/// A section still followed by its trailer, recognized by its terminator.
+fn identify_component(data: &[u8]) -> Option<Identification> {
+    let terminator = data.get(data.len().checked_sub(7)?..)?;
+    let name = component_for_terminator(terminator)?;
+    let (inside, span) = Trailer::span(name);
+    let start = data.len().checked_sub(span)?;
+    let trailer = Trailer::parse(name, &data[start..], start as u64)?;
+
+    let end = start + inside;
+    let base = FileInfoBase {
+        length: end as i32,
+        declared_length: end as i32,
+        offset: 0,
+        version: trailer.version(),
+    };
+    let info = if name.starts_with("gps_db") {
+        let info = read_gps_db_info(&trailer.raw()[..12], base)?;
+        FileInfo::GpsDb(info)
+    } else {
+        FileInfo::Base(base)
+    };
+
+    let mut identification = Identification::new(FileType::Component);
+    identification.trimmed_length = Some(end);
+    identification.version = Some(trailer.version());
+    identification.model = trailer.model();
+    identification.country = match info {
+        FileInfo::GpsDb(info) => info.country,
+        FileInfo::Base(_) => None,
+    };
+    identification.kind = FWFileKind::from_name(name, info);
+    Some(identification)
+}
+
+/// A GPS DB without terminator, recognized by the identify string of its trailer.
+fn identify_gps_db(data: &[u8]) -> Option<Identification> {
+    let block = data.get(data.len().checked_sub(12)?..)?;
+    let identify = String::from_utf8_lossy(&block[8..]);
+    if !OLD_FILE_GPS_DB_IDENTIFY_STR.contains(&&*identify)
+        && !NEW_FILE_GPS_DB_IDENTIFY_STR.contains(&&*identify)
+    {
+        return None;
+    }
+
+    let info = read_gps_db_info(
+        block,
+        FileInfoBase {
+            length: data.len() as i32,
+            declared_length: data.len() as i32,
+            offset: 0,
+            version: 0,
+        },
+    )?;
+    let mut identification = Identification::new(FileType::GpsDb);
+    identification.version = Some(info.info.version);
+    identification.country = info.country;
+    identification.kind = Some(FWFileKind::GpsDb(info));
+    Some(identification)
+}
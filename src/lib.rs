@@ -0,0 +1,19 @@
+pub mod builder;
+pub mod capi;
+pub mod carve;
+pub mod checksum;
+pub mod edit;
+pub mod file;
+pub mod format;
+pub mod gpsdb;
+pub mod hash;
+pub mod identify;
+pub mod lint;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod sound;
+#[cfg(any(test, feature = "synth"))]
+pub mod synth;
+pub mod trailer;
+pub mod util;
+pub mod view;
@@ -0,0 +1,305 @@
+use crate::file::{db_terminator, FWFile, FormatGeneration, UnidenFirmware};
+use crate::format::{trailer_length, RDModel, SECTION_TAGS};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use ufwt_core::section::Header;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Severity> {
+        match name {
+            "info" => Some(Severity::Info),
+            "warning" => Some(Severity::Warning),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Something suspicious about a BLOB, identified by a stable `code`.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Finding {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub offset: Option<u64>,
+    pub component: Option<&'static str>,
+    pub message: String,
+}
+
+impl Finding {
+    fn new(severity: Severity, code: &'static str, message: String) -> Finding {
+        Finding {
+            severity,
+            code,
+            offset: None,
+            component: None,
+            message,
+        }
+    }
+
+    fn at(mut self, offset: usize) -> Finding {
+        self.offset = Some(offset as u64);
+        self
+    }
+
+    fn in_component(mut self, file: &FWFile) -> Finding {
+        self.component = Some(file.kind().to_name());
+        self
+    }
+}
+
+/// Check a BLOB read by a lenient [`UnidenFirmware::read_buffer`] for anything
+/// that parsed but looks wrong, as well as the problems lenient parsing skipped.
+pub fn lint(firmware: &UnidenFirmware) -> Vec<Finding> {
+    let mut findings = vec![];
+    let buffer = &firmware.buffer;
+
+    // byte ranges accounted for by the header, tags, payloads and trailers
+    let mut covered = vec![];
+    if let Some(metadata) = firmware.metadata.as_ref() {
+        if !firmware.files.is_empty() {
+            let header = match metadata.generation {
+                FormatGeneration::GpsDbPackage => 0,
+                _ => Header::parse(buffer).map_or(0, |header| header.size()),
+            };
+            covered.push((0, header));
+        }
+        if metadata.generation == FormatGeneration::Merged {
+            covered.push((buffer.len() - 12, buffer.len()));
+        }
+    }
+
+    let mut models = vec![];
+    for file in firmware.files() {
+        let name = file.kind().to_name();
+        let info = file.info().base();
+        let offset = info.offset() as usize;
+        let end = offset + info.length() as usize;
+        let tagged = SECTION_TAGS.iter().any(|(_, n, _)| *n == name);
+
+        if file.damaged() {
+            let cause = firmware
+                .diagnostics()
+                .iter()
+                .find(|diagnostic| diagnostic.offset == offset as u64)
+                .map_or(String::new(), |diagnostic| {
+                    format!(" ({})", diagnostic.message)
+                });
+            findings.push(
+                Finding::new(
+                    Severity::Error,
+                    "damaged-component",
+                    format!("{} could only be recovered partially{}", name, cause),
+                )
+                .at(offset)
+                .in_component(file),
+            );
+        }
+
+        let declared = info.declared_length().max(0) as usize;
+        if offset + declared > buffer.len() {
+            findings.push(
+                Finding::new(
+                    Severity::Error,
+                    "length-exceeds-file",
+                    format!(
+                        "{} declares {} bytes but only {} are left in the file",
+                        name,
+                        declared,
+                        buffer.len().saturating_sub(offset)
+                    ),
+                )
+                .at(offset)
+                .in_component(file),
+            );
+        }
+
+        covered.push((
+            if tagged { offset - 12 } else { offset },
+            (end + trailer_length(name)).min(buffer.len()),
+        ));
+
+        if info.version() == -1 {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    "unknown-version",
+                    format!("{} has an erased (0xFFFF) version", name),
+                )
+                .at(end)
+                .in_component(file),
+            );
+        }
+
+        if db_terminator(name).is_some() || file.damaged() {
+            continue;
+        }
+
+        // padded components: model bits and padding
+        let Some(trailer) = file.trailer() else {
+            continue;
+        };
+        let word = i16::from_le_bytes(trailer.raw()[0..2].try_into().unwrap());
+        let model = trailer.model().unwrap();
+        if model == RDModel::Unknown && word != -1 {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    "unknown-model",
+                    format!(
+                        "{} has unknown model bits {:#04x}",
+                        name,
+                        (word >> 10) & 0x3F
+                    ),
+                )
+                .at(end)
+                .in_component(file),
+            );
+        } else if model != RDModel::Unknown {
+            models.push((file, model));
+        }
+
+        let padding = &buffer[(offset + declared).min(end)..end];
+        let unexpected = padding
+            .iter()
+            .filter(|b| **b != 0xFF && **b != 0x00)
+            .count();
+        if unexpected != 0 {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    "nonstandard-padding",
+                    format!(
+                        "{} has {} padding bytes other than 0xFF or 0x00",
+                        name, unexpected
+                    ),
+                )
+                .at(offset + declared)
+                .in_component(file),
+            );
+        } else if padding.contains(&0xFF) && padding.contains(&0x00) {
+            findings.push(
+                Finding::new(
+                    Severity::Info,
+                    "mixed-padding",
+                    format!("{} is padded with both 0xFF and 0x00", name),
+                )
+                .at(offset + declared)
+                .in_component(file),
+            );
+        }
+    }
+
+    if let Some((first, model)) = models.first() {
+        for (file, other) in &models[1..] {
+            if other != model {
+                findings.push(
+                    Finding::new(
+                        Severity::Warning,
+                        "model-mismatch",
+                        format!(
+                            "{} is built for {} but {} for {}",
+                            file.kind().to_name(),
+                            other.to_name(),
+                            first.kind().to_name(),
+                            model.to_name()
+                        ),
+                    )
+                    .in_component(file),
+                );
+            }
+        }
+    }
+
+    // whatever starts where a section ends should be another section or `NMGF`
+    let mut i = 0;
+    while i < covered.len() {
+        let section_end = covered[i].1;
+        i += 1;
+        let Some(tag) = buffer.get(section_end..section_end + 4) else {
+            continue;
+        };
+        if tag == b"NMGF" {
+            if section_end + 12 != buffer.len() {
+                findings.push(
+                    Finding::new(
+                        Severity::Warning,
+                        "nmgf-not-at-end",
+                        "NMGF record is not at the end of the file".into(),
+                    )
+                    .at(section_end),
+                );
+                covered.push((section_end, section_end + 12));
+            }
+        } else if !SECTION_TAGS.iter().any(|(t, _, _)| t.as_bytes() == tag)
+            && !covered.iter().any(|(start, _)| *start == section_end)
+        {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    "unknown-tag",
+                    format!("Unknown section tag {:?}", String::from_utf8_lossy(tag)),
+                )
+                .at(section_end),
+            );
+        }
+    }
+
+    covered.sort();
+    let mut position = 0;
+    for (start, end) in covered {
+        if start > position {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    "unread-gap",
+                    format!("{} bytes not belonging to any section", start - position),
+                )
+                .at(position),
+            );
+        }
+        position = position.max(end);
+    }
+    if position < buffer.len() {
+        findings.push(
+            Finding::new(
+                Severity::Warning,
+                "unread-gap",
+                format!("{} bytes after the last section", buffer.len() - position),
+            )
+            .at(position),
+        );
+    }
+
+    // problems lenient parsing skipped over that no check above explains
+    for diagnostic in firmware.diagnostics() {
+        if !findings
+            .iter()
+            .any(|finding| finding.offset == Some(diagnostic.offset))
+        {
+            findings.push(
+                Finding::new(Severity::Error, "parse-error", diagnostic.message.clone())
+                    .at(diagnostic.offset as usize),
+            );
+        }
+    }
+
+    findings.sort_by_key(|finding| (finding.offset, std::cmp::Reverse(finding.severity)));
+    findings
+}
@@ -1,11 +1,19 @@
-mod file;
-mod format;
-mod util;
-use std::{fs, path};
+use std::io::Read;
+use std::{fs, io, path};
 
+use anyhow::Context;
 use clap::{Parser, Subcommand};
 
-use crate::file::UnidenFirmware;
+use ufwt::carve::{carve, write_sections, CarvedSection};
+use ufwt::checksum::{find_in_corpus, CorpusMatch};
+use ufwt::file::{handle_gpsdb_file_info, ExtractMode, FWFileKind, UnidenFirmware};
+use ufwt::format::{GpsDbCountry, RDModel};
+use ufwt::gpsdb::{read_pois, GpsDb, GpsDbDiff, Poi};
+use ufwt::hash::{ComponentHashes, KnownHashes};
+use ufwt::identify::{identify, identify_extracted, FileType, Identification};
+use ufwt::lint::{lint, Finding, Severity};
+use ufwt::sound::SoundDb;
+use ufwt::util::to_hex;
 
 #[derive(Parser, Debug)]
 #[command(version = "0.1.0")]
@@ -21,29 +29,234 @@ struct Args {
     /// Print read intervals
     #[arg(short, long)]
     intervals: bool,
+
+    /// Skip over damaged sections instead of failing (parse, extract and sounds)
+    #[arg(short, long)]
+    lenient: bool,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum SubCmd {
     Extract(ExtractSubcommand),
     Parse(ParseSubcommand),
+    Replace(ReplaceSubcommand),
+    SetVersion(SetVersionSubcommand),
+    Sounds(SoundsSubcommand),
+    BuildSounds(BuildSoundsSubcommand),
+    ImportPois(ImportPoisSubcommand),
+    GpsdbDiff(GpsdbDiffSubcommand),
+    Identify(IdentifySubcommand),
+    Carve(CarveSubcommand),
+    Lint(LintSubcommand),
+    FindChecksum(FindChecksumSubcommand),
 }
 
 /// Extract the contents of a firmware BLOB
 #[derive(Parser, Debug)]
-struct ExtractSubcommand {
-    /// Input firmware BLOB
+pub struct ExtractSubcommand {
+    /// Input firmware BLOB, - for stdin
     firmware: path::PathBuf,
 
     /// Output directory
     out_dir: Option<path::PathBuf>,
+
+    /// Write only the payload length declared in the header or section tag
+    #[arg(long, conflicts_with = "raw")]
+    trim: bool,
+
+    /// Write the padded payload followed by its trailer and terminator
+    #[arg(long)]
+    raw: bool,
 }
 
 /// View the contents of a firmware BLOB
 #[derive(Parser, Debug)]
-struct ParseSubcommand {
-    /// Input firmware BLOB
+pub struct ParseSubcommand {
+    /// Input firmware BLOB, - for stdin
+    firmware: path::PathBuf,
+
+    /// Show offsets, lengths and trailers of the embedded files
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Known hashes JSON, added to the bundled ones, see `KnownHashes`
+    #[arg(long)]
+    hash_db: Vec<path::PathBuf>,
+}
+
+/// Replace a single component inside a firmware BLOB
+#[derive(Parser, Debug)]
+pub struct ReplaceSubcommand {
+    /// Input firmware BLOB, - for stdin
+    firmware: path::PathBuf,
+
+    /// Component to replace (e.g. dsp_nu)
+    #[arg(short, long)]
+    component: String,
+
+    /// Replacement component image as written by `extract --trim`, - for stdin
+    #[arg(short, long)]
+    with: path::PathBuf,
+
+    /// Output firmware BLOB
+    #[arg(short, long)]
+    output: path::PathBuf,
+
+    /// New trailer version (the existing one is kept if omitted)
+    #[arg(long)]
+    trailer_version: Option<i16>,
+}
+
+/// Rewrite a component's trailer version or the BLOB format version
+#[derive(Parser, Debug)]
+pub struct SetVersionSubcommand {
+    /// Input firmware BLOB, - for stdin
+    firmware: path::PathBuf,
+
+    /// Component to update (e.g. dsp_nu), or `nmgf` for the BLOB format version
+    component: String,
+
+    /// New version
+    value: i64,
+
+    /// New model for the trailer's model bits (e.g. R8)
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Output firmware BLOB
+    #[arg(short, long)]
+    output: path::PathBuf,
+}
+
+/// Decode the sound DBs of a firmware BLOB into WAV prompts
+#[derive(Parser, Debug)]
+pub struct SoundsSubcommand {
+    /// Input firmware BLOB, - for stdin
     firmware: path::PathBuf,
+
+    /// Output directory, each sound DB is written to its own subdirectory
+    out_dir: path::PathBuf,
+
+    /// Only decode this sound DB (e.g. sound_dbnu)
+    #[arg(short, long)]
+    component: Option<String>,
+}
+
+/// Build a sound DB from an index JSON and its WAV prompts
+#[derive(Parser, Debug)]
+pub struct BuildSoundsSubcommand {
+    /// Index JSON, as written by the `sounds` command
+    index: path::PathBuf,
+
+    /// Sound DB to build (sound_dbnu, sound_dbla1 or sound_dbla2)
+    #[arg(short, long)]
+    component: String,
+
+    /// Output sound DB, suitable for `replace --with`
+    #[arg(short, long)]
+    output: path::PathBuf,
+
+    /// Trailer version (the index version is kept if omitted)
+    #[arg(long)]
+    trailer_version: Option<i16>,
+}
+
+/// Build an old encoding GPS DB from a CSV, GPX or GeoJSON list of POIs
+#[derive(Parser, Debug)]
+pub struct ImportPoisSubcommand {
+    /// Input POIs (.csv, .gpx or .geojson)
+    pois: path::PathBuf,
+
+    /// Target country (US, NZ or IL)
+    #[arg(short, long)]
+    country: String,
+
+    /// GPS DB version written to the trailer
+    #[arg(long)]
+    db_version: i32,
+
+    /// Output GPS DB, or firmware BLOB with `--insert`
+    #[arg(short, long)]
+    output: path::PathBuf,
+
+    /// Firmware BLOB to insert (or replace) the GPS DB into
+    #[arg(long)]
+    insert: Option<path::PathBuf>,
+}
+
+/// Compare two GPS DBs and report added, removed and modified cameras
+#[derive(Parser, Debug)]
+pub struct GpsdbDiffSubcommand {
+    /// Old GPS DB (gps_db.bin) or GPS DB package, - for stdin
+    old: path::PathBuf,
+
+    /// New GPS DB (gps_db.bin) or GPS DB package, - for stdin
+    new: path::PathBuf,
+
+    /// Maximum distance in meters between two matching cameras
+    #[arg(short, long, default_value_t = 25.0)]
+    tolerance: f64,
+
+    /// Write the delta as GeoJSON
+    #[arg(long)]
+    geojson: Option<path::PathBuf>,
+
+    /// List every changed camera
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+/// Work out what kind of firmware file an input is
+#[derive(Parser, Debug)]
+pub struct IdentifySubcommand {
+    /// Input files, - for stdin. Extracted components are matched against the BLOBs among them
+    #[arg(required = true)]
+    files: Vec<path::PathBuf>,
+
+    /// Known hashes JSON, added to the bundled ones, see `KnownHashes`
+    #[arg(long)]
+    hash_db: Vec<path::PathBuf>,
+}
+
+/// Recover firmware sections from a partial download or flash dump
+#[derive(Parser, Debug)]
+pub struct CarveSubcommand {
+    /// Input file, - for stdin
+    input: path::PathBuf,
+
+    /// Output directory
+    out_dir: Option<path::PathBuf>,
+
+    /// Skip sections below this confidence score
+    #[arg(short, long, default_value_t = 0.0)]
+    min_confidence: f32,
+}
+
+/// Report suspicious conditions in a firmware BLOB
+#[derive(Parser, Debug)]
+pub struct LintSubcommand {
+    /// Input firmware BLOB, - for stdin
+    firmware: path::PathBuf,
+
+    /// Exit with status 1 on findings of this severity or above (error, warning, info)
+    #[arg(short, long, default_value = "error")]
+    fail_on: String,
+
+    /// Print findings as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// Look for checksums over the components of a corpus of firmware BLOBs
+#[derive(Parser, Debug)]
+pub struct FindChecksumSubcommand {
+    /// Input firmware BLOBs, - for stdin
+    #[arg(required = true)]
+    firmware: Vec<path::PathBuf>,
+
+    /// Also list checksums matching in only some of the BLOBs
+    #[arg(short, long)]
+    verbose: bool,
 }
 
 fn main() {
@@ -51,7 +264,8 @@ fn main() {
 
     match cmd.subcmd {
         SubCmd::Extract(args) => {
-            let mut firmware = UnidenFirmware::from(&args.firmware).unwrap();
+            let mut firmware = open_firmware(&args.firmware);
+            firmware.lenient = cmd.lenient;
             firmware.read_buffer().unwrap();
 
             print_fw_contents(&firmware, false);
@@ -62,28 +276,547 @@ fn main() {
                 })
             }
             if let Some(out_dir) = args.out_dir.as_ref().cloned() {
-                firmware.extract_to(out_dir.as_path());
+                let mode = if args.trim {
+                    ExtractMode::Trimmed
+                } else if args.raw {
+                    ExtractMode::Raw
+                } else {
+                    ExtractMode::Padded
+                };
+                firmware.extract_to(out_dir.as_path(), mode);
             }
             if cmd.intervals {
                 firmware.print_intervals();
             }
         }
         SubCmd::Parse(args) => {
-            let mut firmware: UnidenFirmware = UnidenFirmware::from(&args.firmware).unwrap();
+            let mut firmware = open_firmware(&args.firmware);
+            firmware.lenient = cmd.lenient;
             firmware.read_buffer().unwrap();
             print_fw_contents(&firmware, cmd.intervals);
+            if args.verbose {
+                print_trailers(&firmware);
+            }
+            let known = load_known_hashes(&args.hash_db);
+            let hashes = ComponentHashes::of_components(&firmware);
+            print_hashes(&hashes);
+            print_known_releases(&known, &hashes);
+        }
+        SubCmd::Replace(args) => {
+            let mut firmware = open_firmware(&args.firmware);
+            firmware.read_buffer().unwrap();
+
+            let data = read_input(&args.with);
+            let blob = firmware
+                .replace_component(&args.component, &data, args.trailer_version)
+                .unwrap_or_else(|e| panic!("Couldn't replace {}: {}", args.component, e));
+            fs::write(&args.output, &blob).unwrap_or_else(|_| {
+                panic!("Couldn't write output file: {}", args.output.display())
+            });
+
+            let mut firmware = UnidenFirmware::from(&args.output).unwrap();
+            firmware.read_buffer().unwrap();
+            print_fw_contents(&firmware, cmd.intervals);
+        }
+        SubCmd::SetVersion(args) => {
+            let mut firmware = open_firmware(&args.firmware);
+            firmware.read_buffer().unwrap();
+
+            let model = args.model.as_ref().map(|name| {
+                RDModel::from_name(name).unwrap_or_else(|| panic!("Unknown model: {}", name))
+            });
+            let blob = if args.component == "nmgf" {
+                if model.is_some() {
+                    panic!("The NMGF record has no model");
+                }
+                let version = i32::try_from(args.value)
+                    .unwrap_or_else(|_| panic!("Invalid format version: {}", args.value));
+                firmware.set_format_version(version)
+            } else {
+                firmware.set_version(&args.component, args.value, model)
+            }
+            .unwrap_or_else(|e| panic!("Couldn't set version of {}: {}", args.component, e));
+            fs::write(&args.output, &blob).unwrap_or_else(|_| {
+                panic!("Couldn't write output file: {}", args.output.display())
+            });
+
+            let mut firmware = UnidenFirmware::from(&args.output).unwrap();
+            firmware.read_buffer().unwrap();
+            print_fw_contents(&firmware, cmd.intervals);
+        }
+        SubCmd::Sounds(args) => {
+            let mut firmware = open_firmware(&args.firmware);
+            firmware.lenient = cmd.lenient;
+            firmware.read_buffer().unwrap();
+
+            let sound_dbs = firmware.components().filter(|component| {
+                matches!(
+                    component.kind(),
+                    FWFileKind::SoundDbnu(_)
+                        | FWFileKind::SoundDbla1(_)
+                        | FWFileKind::SoundDbla2(_)
+                ) && args
+                    .component
+                    .as_ref()
+                    .is_none_or(|name| name == component.kind().to_name())
+            });
+            for component in sound_dbs {
+                let name = component.kind().to_name();
+                let sound_db = match SoundDb::parse(component.payload()) {
+                    Ok(sound_db) => sound_db,
+                    Err(e) => {
+                        eprintln!("Couldn't decode {}: {}", name, e);
+                        continue;
+                    }
+                };
+                sound_db
+                    .write_to(&args.out_dir.join(name))
+                    .unwrap_or_else(|e| panic!("Couldn't write prompts of {}: {}", name, e));
+                println!(
+                    "{}: {} prompts, {} at {} Hz (version {})",
+                    name,
+                    sound_db.prompts.len(),
+                    sound_db.format.to_name(),
+                    sound_db.sample_rate,
+                    sound_db.version
+                );
+            }
+        }
+        SubCmd::BuildSounds(args) => {
+            let mut sound_db = SoundDb::from_index(&args.index).unwrap_or_else(|e| {
+                panic!("Couldn't load prompts from {}: {}", args.index.display(), e)
+            });
+            if let Some(version) = args.trailer_version {
+                sound_db.version = version;
+            }
+
+            let section = sound_db
+                .build_section(&args.component)
+                .unwrap_or_else(|e| panic!("Couldn't build {}: {}", args.component, e));
+            fs::write(&args.output, &section).unwrap_or_else(|_| {
+                panic!("Couldn't write output file: {}", args.output.display())
+            });
+            println!(
+                "{}: {} prompts, {} at {} Hz (version {})",
+                args.component,
+                sound_db.prompts.len(),
+                sound_db.format.to_name(),
+                sound_db.sample_rate,
+                sound_db.version
+            );
+        }
+        SubCmd::ImportPois(args) => {
+            let country = GpsDbCountry::from_name(&args.country)
+                .unwrap_or_else(|| panic!("Unknown country: {}", args.country));
+            let pois = read_pois(&args.pois).unwrap_or_else(|e| {
+                panic!("Couldn't read POIs from {}: {}", args.pois.display(), e)
+            });
+            let gps_db = GpsDb {
+                country,
+                version: args.db_version,
+                pois,
+            };
+            let section = gps_db
+                .build_section()
+                .unwrap_or_else(|e| panic!("Couldn't build GPS DB: {}", e));
+
+            let out = if let Some(blob) = args.insert.as_ref() {
+                let mut firmware = open_firmware(blob);
+                firmware.read_buffer().unwrap();
+                if firmware.find_file("gps_db").is_some() {
+                    firmware.replace_component("gps_db", &section, None)
+                } else {
+                    firmware.insert_section("GPSD", "gps_db", &section)
+                }
+                .unwrap_or_else(|e| panic!("Couldn't insert GPS DB: {}", e))
+            } else {
+                section
+            };
+            fs::write(&args.output, &out).unwrap_or_else(|_| {
+                panic!("Couldn't write output file: {}", args.output.display())
+            });
+            println!(
+                "GPS DB ({}): {} POIs (version {})",
+                country.to_name(),
+                gps_db.pois.len(),
+                gps_db.version
+            );
+        }
+        SubCmd::GpsdbDiff(args) => gpsdb_diff(&args).unwrap_or_else(|e| {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }),
+        SubCmd::Identify(args) => {
+            let known = load_known_hashes(&args.hash_db);
+            let inputs: Vec<_> = args.files.iter().map(|path| read_input(path)).collect();
+            // BLOBs given alongside extracted components tell where they come from
+            let blobs: Vec<_> = inputs
+                .iter()
+                .filter(|data| {
+                    matches!(
+                        identify(data).file_type,
+                        FileType::MergedBlob | FileType::LegacyBlob | FileType::GpsDbPackage
+                    )
+                })
+                .filter_map(|data| {
+                    let mut firmware = UnidenFirmware::from_bytes(data);
+                    firmware.read_buffer().ok().map(|_| firmware)
+                })
+                .collect();
+            for (path, data) in args.files.iter().zip(&inputs) {
+                let identification = blobs
+                    .iter()
+                    .find_map(|firmware| identify_extracted(data, firmware))
+                    .unwrap_or_else(|| identify(data));
+                print_identification(path, &identification);
+                let hashes = ComponentHashes::of(identification.payload(data));
+                for release in known.lookup(&hashes, None) {
+                    println!(
+                        "   Known release: {} of Uniden {} release {}",
+                        release.component,
+                        release.model.to_name(),
+                        release.release
+                    );
+                }
+            }
+        }
+        SubCmd::Carve(args) => {
+            let data = read_input(&args.input);
+            let mut sections = carve(&data);
+            sections.retain(|section| section.confidence >= args.min_confidence);
+
+            println!("Carved sections: {}", sections.len());
+            for section in &sections {
+                print_carved_section(section);
+            }
+
+            if let Some(dir) = args.out_dir.as_ref() {
+                write_sections(&data, &sections, dir).unwrap_or_else(|e| {
+                    panic!(
+                        "Couldn't write to output directory {}: {}",
+                        dir.display(),
+                        e
+                    )
+                });
+            }
+        }
+        SubCmd::Lint(args) => {
+            let threshold = Severity::from_name(&args.fail_on)
+                .unwrap_or_else(|| panic!("Unknown severity: {}", args.fail_on));
+
+            let mut firmware = open_firmware(&args.firmware);
+            firmware.lenient = true;
+            firmware.read_buffer().unwrap();
+
+            let findings = lint(&firmware);
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&findings).unwrap());
+            } else {
+                for finding in &findings {
+                    print_finding(finding);
+                }
+                println!("{} findings", findings.len());
+            }
+
+            if findings.iter().any(|finding| finding.severity >= threshold) {
+                std::process::exit(1);
+            }
+        }
+        SubCmd::FindChecksum(args) => {
+            let mut firmwares = vec![];
+            for path in &args.firmware {
+                let mut firmware = open_firmware(path);
+                firmware.lenient = cmd.lenient;
+                match firmware.read_buffer() {
+                    Ok(()) => firmwares.push(firmware),
+                    Err(e) => eprintln!("Couldn't parse {}: {}", path.display(), e),
+                }
+            }
+
+            let corpus = find_in_corpus(&firmwares);
+            let (consistent, partial): (Vec<_>, Vec<_>) =
+                corpus.iter().partition(|found| found.is_consistent());
+            println!("BLOBs: {}", firmwares.len());
+            if consistent.is_empty() {
+                println!("No checksum matches in every BLOB");
+            } else {
+                println!("Checksums matching in every BLOB:");
+                for found in &consistent {
+                    print_corpus_match(found);
+                }
+            }
+            if args.verbose && !partial.is_empty() {
+                println!("Partial matches:");
+                for found in &partial {
+                    print_corpus_match(found);
+                }
+            }
+        }
+    }
+}
+
+fn print_corpus_match(found: &CorpusMatch) {
+    println!(
+        "   - {} ({}/{} BLOBs)",
+        found.candidate.describe(),
+        found.matches,
+        found.blobs
+    );
+}
+
+fn print_finding(finding: &Finding) {
+    let mut location = String::new();
+    if let Some(offset) = finding.offset {
+        location.push_str(&format!(" {:#010x}", offset));
+    }
+    if let Some(component) = finding.component {
+        location.push_str(&format!(" {}", component));
+    }
+    println!(
+        "{}[{}]{}: {}",
+        finding.severity.to_name(),
+        finding.code,
+        location,
+        finding.message
+    );
+}
+
+fn print_carved_section(section: &CarvedSection) {
+    println!(
+        "   {:#010x}-{:#010x} {} (confidence {:.2}, from {})",
+        section.start,
+        section.end,
+        section.name,
+        section.confidence,
+        section.method.to_name()
+    );
+    if let Some(version) = section.version {
+        println!("      Version: {}", version);
+    }
+    if let Some(model) = section.model {
+        println!("      Model: Uniden {}", model.to_name());
+    }
+}
+
+fn print_identification(path: &path::Path, identification: &Identification) {
+    println!("{}: {}", path.display(), identification.file_type.to_name());
+    if let Some(kind) = identification.kind.as_ref() {
+        println!("   Component: {}", kind.to_name());
+    }
+    if let Some(mode) = identification.mode {
+        println!("   Extracted as: {}", mode.to_name());
+    }
+    if let Some(format_version) = identification.format_version {
+        println!("   BLOB format version: {}", format_version);
+    }
+    if let Some(model) = identification.model {
+        println!("   Model: Uniden {}", model.to_name());
+    }
+    if let Some(country) = identification.country {
+        println!("   Country: {}", country.to_name());
+    }
+    if let Some(version) = identification.version {
+        println!("   Version: {}", version);
+    }
+    if !identification.components.is_empty() {
+        println!(
+            "   Embedded files: {}",
+            identification.components.join(", ")
+        );
+    }
+    let commands = identification.commands();
+    if !commands.is_empty() {
+        println!("   Applicable commands: {}", commands.join(", "));
+    }
+}
+
+/// Compare the GPS DBs given to `gpsdb-diff`.
+fn gpsdb_diff(args: &GpsdbDiffSubcommand) -> anyhow::Result<()> {
+    let read_gps_db = |path: &path::PathBuf| {
+        GpsDb::parse_file(&read_input(path))
+            .with_context(|| format!("Couldn't decode {}", path.display()))
+    };
+    let old = read_gps_db(&args.old)?;
+    let new = read_gps_db(&args.new)?;
+
+    let diff = GpsDbDiff::new(&old.pois, &new.pois, args.tolerance);
+    println!(
+        "Old: {} POIs (version {}), new: {} POIs (version {})",
+        old.pois.len(),
+        old.version,
+        new.pois.len(),
+        new.version
+    );
+    println!(
+        "Added: {}, removed: {}, modified: {}, unchanged: {}",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.modified.len(),
+        diff.unchanged
+    );
+    for (camera, totals) in diff.totals() {
+        println!(
+            "   - {}: +{} -{} ~{}",
+            camera.to_name(),
+            totals.added,
+            totals.removed,
+            totals.modified
+        );
+    }
+
+    if args.verbose {
+        let describe = |poi: &Poi| {
+            format!(
+                "{:.6}, {:.6} {} speed {} heading {}",
+                poi.latitude,
+                poi.longitude,
+                poi.camera.to_name(),
+                poi.speed,
+                poi.heading
+                    .map_or("any".to_string(), |heading| heading.to_string())
+            )
+        };
+        for poi in &diff.added {
+            println!("+ {}", describe(poi));
+        }
+        for poi in &diff.removed {
+            println!("- {}", describe(poi));
+        }
+        for (old, new) in &diff.modified {
+            println!("~ {} -> {}", describe(old), describe(new));
+        }
+    }
+
+    if let Some(path) = args.geojson.as_ref() {
+        let json = serde_json::to_string_pretty(&diff.to_geojson())?;
+        fs::write(path, json)
+            .with_context(|| format!("Couldn't write output file: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Read an input file, `-` standing for stdin.
+fn read_input(path: &path::Path) -> Vec<u8> {
+    if path.as_os_str() == "-" {
+        let mut data = vec![];
+        io::stdin()
+            .read_to_end(&mut data)
+            .unwrap_or_else(|e| panic!("Couldn't read stdin: {}", e));
+        data
+    } else {
+        fs::read(path).unwrap_or_else(|_| panic!("Couldn't read input file: {}", path.display()))
+    }
+}
+
+fn open_firmware(path: &path::Path) -> UnidenFirmware {
+    UnidenFirmware::from_buffer(read_input(path))
+}
+
+/// The bundled known hashes and those of the hash DBs at `paths`.
+fn load_known_hashes(paths: &[path::PathBuf]) -> KnownHashes {
+    let mut known = KnownHashes::bundled();
+    for path in paths {
+        known
+            .load(path)
+            .unwrap_or_else(|e| panic!("Couldn't load hash DB {}: {}", path.display(), e));
+    }
+    known
+}
+
+fn print_hashes(hashes: &[(&'static str, ComponentHashes)]) {
+    println!("Hashes:");
+    for (name, hashes) in hashes {
+        println!("   - {}: crc32 {}", name, hashes.crc32_hex());
+        println!("     sha1 {}", to_hex(&hashes.sha1));
+        println!("     sha256 {}", to_hex(&hashes.sha256));
+    }
+}
+
+fn print_known_releases(known: &KnownHashes, hashes: &[(&'static str, ComponentHashes)]) {
+    let matches: Vec<_> = hashes
+        .iter()
+        .flat_map(|(name, hashes)| {
+            known
+                .lookup(hashes, Some(name))
+                .map(move |release| (name, release))
+        })
+        .collect();
+    if matches.is_empty() {
+        return;
+    }
+    println!("Known releases:");
+    for (name, release) in matches {
+        println!(
+            "   - {}: Uniden {} release {}",
+            name,
+            release.model.to_name(),
+            release.release
+        );
+    }
+}
+
+fn print_trailers(firmware: &UnidenFirmware) {
+    println!("Layout:");
+    for file in firmware.files() {
+        let info = file.info().base();
+        println!(
+            "   - {}: offset {:#010x}, length {:#x} (declared {:#x})",
+            file.kind().to_name(),
+            info.offset(),
+            info.length(),
+            info.declared_length()
+        );
+        let Some(trailer) = file.trailer() else {
+            println!("     trailer: missing");
+            continue;
+        };
+        println!(
+            "     trailer at {:#010x}: {}",
+            trailer.offset(),
+            to_hex(trailer.raw())
+        );
+        println!(
+            "     model {}, version {}, terminator {}",
+            trailer.model().map_or("none", |model| model.to_name()),
+            trailer.version(),
+            trailer.terminator()
+        );
+        if !trailer.extra().is_empty() {
+            println!("     extra bytes: {}", to_hex(trailer.extra()));
         }
     }
 }
 
 fn print_fw_contents(firmware: &UnidenFirmware, intervals: bool) {
     let metadata = firmware.metadata.as_ref().unwrap();
-    println!("BLOB format version: {}", metadata.format_version);
+    println!("BLOB format: {}", metadata.generation.to_name());
+    if let Some(format_version) = metadata.format_version {
+        println!("BLOB format version: {}", format_version);
+    }
     println!("Model: Uniden {}", metadata.model.to_name());
     println!("Embedded files: ");
-    for file in &firmware.files {
-        let name = file.kind.to_file_name();
-        println!("   - {}", name);
+    for file in firmware.files() {
+        let mut name = file.kind().to_file_name();
+        if file.damaged() {
+            name.push_str(" [damaged]");
+        }
+        match handle_gpsdb_file_info(file.kind()) {
+            Some(info) => println!(
+                "   - {} (version {}, {}, {} POIs)",
+                name,
+                info.info().version(),
+                info.country()
+                    .map_or("unknown country", |country| country.to_name()),
+                info.poi()
+            ),
+            None => println!("   - {} (version {})", name, file.info().base().version()),
+        }
+    }
+    if !firmware.diagnostics().is_empty() {
+        println!("Diagnostics:");
+        for diagnostic in firmware.diagnostics() {
+            println!("   - {:#010x}: {}", diagnostic.offset, diagnostic.message);
+        }
     }
     if intervals {
         firmware.print_intervals();
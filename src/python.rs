@@ -0,0 +1,143 @@
+//! Python extension module, built as a wheel with `maturin build --release`.
+
+use crate::file::{handle_gpsdb_file_info, ExtractMode, UnidenFirmware};
+use crate::format;
+use crate::gpsdb::GpsDb;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+
+/// Parse a BLOB into a dict holding its metadata, components and, in lenient
+/// mode, the diagnostics for the damaged parts.
+#[pyfunction]
+#[pyo3(signature = (data, lenient = false))]
+fn parse<'py>(py: Python<'py>, data: &[u8], lenient: bool) -> PyResult<Bound<'py, PyDict>> {
+    let mut firmware = UnidenFirmware::from_bytes(data);
+    firmware.lenient = lenient;
+    firmware.read_buffer()?;
+
+    let blob = PyDict::new(py);
+    let metadata = firmware.metadata.as_ref().unwrap();
+    blob.set_item("model", metadata.model.to_name())?;
+    blob.set_item("generation", metadata.generation.to_name())?;
+    blob.set_item("format_version", metadata.format_version)?;
+
+    let components = PyList::empty(py);
+    for component in firmware.components() {
+        let info = component.file().info().base();
+        let dict = PyDict::new(py);
+        dict.set_item("kind", component.kind().to_name())?;
+        dict.set_item("tag", component.tag())?;
+        dict.set_item("offset", info.offset())?;
+        dict.set_item("length", info.length())?;
+        dict.set_item("declared_length", info.declared_length())?;
+        dict.set_item("version", component.version())?;
+        dict.set_item("damaged", component.file().damaged())?;
+        dict.set_item(
+            "data",
+            PyBytes::new(py, component.slice(ExtractMode::Trimmed)),
+        )?;
+        dict.set_item("padded", PyBytes::new(py, component.payload()))?;
+        dict.set_item(
+            "decoded",
+            component
+                .decoded()
+                .map(|decoded| PyBytes::new(py, &decoded)),
+        )?;
+        if let Some(gps_db) = handle_gpsdb_file_info(component.kind()) {
+            dict.set_item("country", gps_db.country().map(|country| country.to_name()))?;
+            dict.set_item("poi", gps_db.poi())?;
+        }
+        components.append(dict)?;
+    }
+    blob.set_item("components", components)?;
+
+    let diagnostics = PyList::empty(py);
+    for diagnostic in firmware.diagnostics() {
+        let dict = PyDict::new(py);
+        dict.set_item("offset", diagnostic.offset)?;
+        dict.set_item("message", &diagnostic.message)?;
+        diagnostics.append(dict)?;
+    }
+    blob.set_item("diagnostics", diagnostics)?;
+    Ok(blob)
+}
+
+/// `length` bytes of `data` starting at `offset`, `length` defaulting to the rest.
+fn word_range(data: &[u8], offset: usize, length: Option<usize>) -> PyResult<(usize, usize)> {
+    let length = length.unwrap_or(data.len().saturating_sub(offset));
+    match offset.checked_add(length) {
+        Some(end) if end <= data.len() => Ok((offset, length)),
+        _ => Err(PyValueError::new_err(format!(
+            "{} bytes at offset {} are out of range",
+            length, offset
+        ))),
+    }
+}
+
+/// Undo the old model encoding of `length` bytes of `data` starting at `offset`.
+#[pyfunction]
+#[pyo3(signature = (key, data, offset = 0, length = None))]
+fn decode_old_model<'py>(
+    py: Python<'py>,
+    key: u8,
+    data: &[u8],
+    offset: usize,
+    length: Option<usize>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let (offset, length) = word_range(data, offset, length)?;
+    Ok(PyBytes::new(
+        py,
+        &format::decode_old_model(key, data, offset, length),
+    ))
+}
+
+/// Apply the old model encoding to `length` bytes of `data` starting at `offset`.
+#[pyfunction]
+#[pyo3(signature = (key, data, offset = 0, length = None))]
+fn encode_old_model<'py>(
+    py: Python<'py>,
+    key: u8,
+    data: &[u8],
+    offset: usize,
+    length: Option<usize>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let (offset, length) = word_range(data, offset, length)?;
+    Ok(PyBytes::new(
+        py,
+        &format::encode_old_model(key, data, offset, length),
+    ))
+}
+
+/// Parse an old encoding GPS DB, as found in the `data` of a `gps_db` component.
+#[pyfunction]
+fn parse_gps_db<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let gps_db = GpsDb::parse(data)?;
+    let dict = PyDict::new(py);
+    dict.set_item("country", gps_db.country.to_name())?;
+    dict.set_item("version", gps_db.version)?;
+
+    let pois = PyList::empty(py);
+    for poi in &gps_db.pois {
+        let item = PyDict::new(py);
+        item.set_item("latitude", poi.latitude)?;
+        item.set_item("longitude", poi.longitude)?;
+        item.set_item("type", poi.camera.to_name())?;
+        item.set_item("speed", poi.speed)?;
+        item.set_item("heading", poi.heading)?;
+        pois.append(item)?;
+    }
+    dict.set_item("pois", pois)?;
+    Ok(dict)
+}
+
+/// The `ufwt` module, also reachable from Rust with `wrap_pymodule!`.
+#[pymodule]
+pub fn ufwt(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(decode_old_model, module)?)?;
+    module.add_function(wrap_pyfunction!(encode_old_model, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_gps_db, module)?)?;
+    module.add("SOUND_DB_KEY", format::SOUND_DB_KEY)?;
+    Ok(())
+}
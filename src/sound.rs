@@ -0,0 +1,490 @@
+use crate::edit::check_rd_version;
+use crate::file::db_terminator;
+use crate::format::{decode_old_model, encode_old_model, rd_version, SOUND_DB_KEY};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io;
+#[cfg(feature = "serde")]
+use std::{fs, path::Path};
+
+/// Encoded block at the end of every sound DB, the first word holds the version.
+pub const SOUND_DB_TRAILER_LEN: usize = 12;
+
+/// count (4) + format tag (2) + bits per sample (2) + sample rate (4)
+const HEADER_LEN: usize = 12;
+/// offset (4) + length (4)
+const TABLE_ENTRY_LEN: usize = 8;
+
+const IMA_INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const IMA_STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A sound DB not following the assumed prompt table layout.
+fn unexpected_layout(msg: String) -> io::Error {
+    invalid_data(format!(
+        "{}, the sound DB does not follow the assumed prompt table layout",
+        msg
+    ))
+}
+
+/// Sample encoding of the prompts, identified by the WAVE format tag and
+/// bits per sample in the prompt table header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SampleFormat {
+    /// Unsigned 8-bit PCM
+    Pcm8,
+    /// Signed 16-bit little-endian PCM
+    Pcm16,
+    /// 4-bit IMA ADPCM, each prompt starting with a predictor/step index
+    /// preamble whose last byte is 1 when the final byte holds a single sample
+    ImaAdpcm,
+}
+
+impl SampleFormat {
+    fn from_header(format_tag: u16, bits_per_sample: u16) -> Option<Self> {
+        match (format_tag, bits_per_sample) {
+            (1, 8) => Some(SampleFormat::Pcm8),
+            (1, 16) => Some(SampleFormat::Pcm16),
+            (0x11, 4) => Some(SampleFormat::ImaAdpcm),
+            _ => None,
+        }
+    }
+
+    fn to_header(self) -> (u16, u16) {
+        match self {
+            SampleFormat::Pcm8 => (1, 8),
+            SampleFormat::Pcm16 => (1, 16),
+            SampleFormat::ImaAdpcm => (0x11, 4),
+        }
+    }
+
+    /// Encode 16-bit PCM samples in this format.
+    pub fn encode(self, samples: &[i16]) -> Vec<u8> {
+        match self {
+            SampleFormat::Pcm8 => samples.iter().map(|s| ((*s >> 8) + 128) as u8).collect(),
+            SampleFormat::Pcm16 => samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+            SampleFormat::ImaAdpcm => encode_ima_adpcm(samples),
+        }
+    }
+
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            SampleFormat::Pcm8 => "8-bit PCM",
+            SampleFormat::Pcm16 => "16-bit PCM",
+            SampleFormat::ImaAdpcm => "IMA ADPCM",
+        }
+    }
+}
+
+/// A decoded voice prompt database (`sound_dbnu`, `sound_dbla1` or `sound_dbla2`).
+///
+/// The body before the trailer block is encoded with [`SOUND_DB_KEY`]. Once
+/// decoded it is assumed to start with a prompt count, the sample format and
+/// rate, followed by an (offset, length) table whose offsets are relative to
+/// the body. This layout has not been checked against a dump from a real
+/// device, so [`SoundDb::parse`] rejects any DB not following it exactly
+/// rather than guessing.
+pub struct SoundDb {
+    pub version: i16,
+    pub format: SampleFormat,
+    pub sample_rate: u32,
+    pub prompts: Vec<Vec<u8>>,
+}
+
+impl SoundDb {
+    /// Parse a sound DB as extracted from a BLOB, trailer block included.
+    pub fn parse(data: &[u8]) -> io::Result<SoundDb> {
+        if data.len() < SOUND_DB_TRAILER_LEN + HEADER_LEN {
+            return Err(invalid_data("Sound DB is too short".into()));
+        }
+
+        let body_len = (data.len() - SOUND_DB_TRAILER_LEN) & !3;
+        let body = decode_old_model(SOUND_DB_KEY, data, 0, body_len);
+        let trailer = decode_old_model(SOUND_DB_KEY, data, data.len() - SOUND_DB_TRAILER_LEN, 4);
+        let version = rd_version(i32::from_le_bytes(trailer.try_into().unwrap()) as i16);
+
+        let word = |pos: usize| u32::from_le_bytes(body[pos..pos + 4].try_into().unwrap());
+        let half = |pos: usize| u16::from_le_bytes(body[pos..pos + 2].try_into().unwrap());
+
+        let count = word(0) as usize;
+        let format = SampleFormat::from_header(half(4), half(6)).ok_or_else(|| {
+            unexpected_layout(format!(
+                "Unknown sample format: tag {:#x}, {} bits per sample",
+                half(4),
+                half(6)
+            ))
+        })?;
+        let sample_rate = word(8);
+        if sample_rate == 0 {
+            return Err(unexpected_layout("Sample rate of 0 Hz".into()));
+        }
+
+        let table_end = count
+            .checked_mul(TABLE_ENTRY_LEN)
+            .and_then(|len| len.checked_add(HEADER_LEN))
+            .filter(|end| *end <= body.len())
+            .ok_or_else(|| {
+                unexpected_layout(format!("Prompt table of {} entries is too large", count))
+            })?;
+
+        // prompts are stored back to back after the table, in table order
+        let mut prompts = Vec::with_capacity(count);
+        let mut next = table_end;
+        for entry in (HEADER_LEN..table_end).step_by(TABLE_ENTRY_LEN) {
+            let offset = word(entry) as usize;
+            let length = word(entry + 4) as usize;
+            let prompt = offset
+                .checked_add(length)
+                .filter(|_| offset == next)
+                .and_then(|end| body.get(offset..end))
+                .ok_or_else(|| {
+                    unexpected_layout(format!(
+                        "Prompt {} at {:#x} ({} bytes) does not follow the previous one at {:#x}",
+                        prompts.len(),
+                        offset,
+                        length,
+                        next
+                    ))
+                })?;
+            next += length;
+            prompts.push(prompt.to_vec());
+        }
+        // the last prompt is followed by at most the padding to a word boundary
+        if body.len() - next >= 4 {
+            return Err(unexpected_layout(format!(
+                "{} bytes follow the last prompt",
+                body.len() - next
+            )));
+        }
+
+        Ok(SoundDb {
+            version,
+            format,
+            sample_rate,
+            prompts,
+        })
+    }
+
+    /// Decode prompt `index` to 16-bit PCM samples.
+    pub fn samples(&self, index: usize) -> Vec<i16> {
+        let data = &self.prompts[index];
+        match self.format {
+            SampleFormat::Pcm8 => data.iter().map(|s| (*s as i16 - 128) << 8).collect(),
+            SampleFormat::Pcm16 => data
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]))
+                .collect(),
+            SampleFormat::ImaAdpcm => decode_ima_adpcm(data),
+        }
+    }
+
+    /// Write every prompt as a numbered WAV file to `directory`, along with an
+    /// `index.json` describing them.
+    #[cfg(feature = "serde")]
+    pub fn write_to(&self, directory: &Path) -> io::Result<SoundDbIndex> {
+        fs::create_dir_all(directory)?;
+
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        let mut prompts = Vec::with_capacity(self.prompts.len());
+        for index in 0..self.prompts.len() {
+            let file = format!("{:03}.wav", index);
+            let samples = self.samples(index);
+
+            let mut writer =
+                hound::WavWriter::create(directory.join(&file), spec).map_err(io::Error::other)?;
+            for sample in &samples {
+                writer.write_sample(*sample).map_err(io::Error::other)?;
+            }
+            writer.finalize().map_err(io::Error::other)?;
+
+            prompts.push(PromptEntry {
+                index,
+                file,
+                samples: samples.len(),
+            });
+        }
+
+        let index = SoundDbIndex {
+            version: self.version,
+            format: self.format,
+            sample_rate: self.sample_rate,
+            prompts,
+        };
+        let json = serde_json::to_string_pretty(&index).map_err(io::Error::other)?;
+        fs::write(directory.join("index.json"), json)?;
+
+        Ok(index)
+    }
+
+    /// Load the prompts listed in an `index.json` written by [`SoundDb::write_to`].
+    ///
+    /// WAV files are looked up next to the index, mixed down to mono and
+    /// resampled to the sample rate of the index.
+    #[cfg(feature = "serde")]
+    pub fn from_index(path: &Path) -> io::Result<SoundDb> {
+        let index: SoundDbIndex =
+            serde_json::from_slice(&fs::read(path)?).map_err(io::Error::other)?;
+        let version = check_rd_version(index.version as i64)?;
+        if index.sample_rate == 0 {
+            return Err(invalid_data("Sample rate of 0 Hz".into()));
+        }
+        let directory = path.parent().unwrap_or(Path::new("."));
+
+        let mut entries = index.prompts.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.index);
+
+        let mut prompts = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let samples = read_wav(&directory.join(&entry.file), index.sample_rate)?;
+            prompts.push(index.format.encode(&samples));
+        }
+
+        Ok(SoundDb {
+            version,
+            format: index.format,
+            sample_rate: index.sample_rate,
+            prompts,
+        })
+    }
+
+    /// Encode the database as stored in a BLOB, trailer block included.
+    pub fn build(&self) -> Vec<u8> {
+        let (format_tag, bits_per_sample) = self.format.to_header();
+        let mut body = Vec::new();
+        body.extend_from_slice(&(self.prompts.len() as u32).to_le_bytes());
+        body.extend_from_slice(&format_tag.to_le_bytes());
+        body.extend_from_slice(&bits_per_sample.to_le_bytes());
+        body.extend_from_slice(&self.sample_rate.to_le_bytes());
+
+        let mut offset = HEADER_LEN + self.prompts.len() * TABLE_ENTRY_LEN;
+        for prompt in &self.prompts {
+            body.extend_from_slice(&(offset as u32).to_le_bytes());
+            body.extend_from_slice(&(prompt.len() as u32).to_le_bytes());
+            offset += prompt.len();
+        }
+        for prompt in &self.prompts {
+            body.extend_from_slice(prompt);
+        }
+        body.resize(body.len().next_multiple_of(4), 0);
+
+        let mut trailer = [0u8; SOUND_DB_TRAILER_LEN];
+        trailer[0..4].copy_from_slice(&(self.version as i32).to_le_bytes());
+
+        let mut out = encode_old_model(SOUND_DB_KEY, &body, 0, body.len());
+        out.extend(encode_old_model(SOUND_DB_KEY, &trailer, 0, trailer.len()));
+        out
+    }
+
+    /// Encode the database followed by the terminator of sound DB `name`
+    /// (e.g. `sound_dbla2`), ready to be inserted into a BLOB.
+    pub fn build_section(&self, name: &str) -> io::Result<Vec<u8>> {
+        let (skip, terminator) = db_terminator(name)
+            .filter(|_| name.starts_with("sound_db"))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Not a sound DB: {}", name),
+                )
+            })?;
+        check_rd_version(self.version as i64)?;
+
+        let mut out = self.build();
+        out.resize(out.len() + skip, 0);
+        out.extend_from_slice(terminator.as_bytes());
+        Ok(out)
+    }
+}
+
+/// Contents of the `index.json` written next to the extracted prompts.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+pub struct SoundDbIndex {
+    pub version: i16,
+    pub format: SampleFormat,
+    pub sample_rate: u32,
+    pub prompts: Vec<PromptEntry>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+pub struct PromptEntry {
+    pub index: usize,
+    pub file: String,
+    pub samples: usize,
+}
+
+/// Read a WAV file as mono 16-bit samples at `sample_rate`.
+#[cfg(feature = "serde")]
+fn read_wav(path: &Path, sample_rate: u32) -> io::Result<Vec<i16>> {
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| io::Error::other(format!("{}: {}", path.display(), e)))?;
+    let spec = reader.spec();
+    if spec.sample_rate == 0 {
+        return Err(invalid_data(format!(
+            "{}: sample rate of 0 Hz",
+            path.display()
+        )));
+    }
+
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Int => {
+            let shift = spec.bits_per_sample as i32 - 16;
+            reader
+                .samples::<i32>()
+                .map(|s| {
+                    s.map(|s| match shift {
+                        0.. => (s >> shift) as i16,
+                        _ => (s << -shift) as i16,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        }
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .map(|s| s.map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16))
+            .collect::<Result<Vec<_>, _>>(),
+    }
+    .map_err(io::Error::other)?;
+
+    let channels = spec.channels.max(1) as usize;
+    let mono = samples
+        .chunks(channels)
+        .map(|frame| (frame.iter().map(|s| *s as i32).sum::<i32>() / frame.len() as i32) as i16)
+        .collect::<Vec<_>>();
+
+    Ok(resample(&mono, spec.sample_rate, sample_rate))
+}
+
+/// Linear interpolation resampler, good enough for voice prompts.
+#[cfg(feature = "serde")]
+fn resample(samples: &[i16], from: u32, to: u32) -> Vec<i16> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let len = (samples.len() as u64 * to as u64 / from as u64) as usize;
+    let step = from as f64 / to as f64;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let left = pos as usize;
+            let right = (left + 1).min(samples.len() - 1);
+            let frac = pos - left as f64;
+            (samples[left] as f64 * (1.0 - frac) + samples[right] as f64 * frac) as i16
+        })
+        .collect()
+}
+
+/// Encode samples as a mono IMA ADPCM stream, see [`decode_ima_adpcm`].
+fn encode_ima_adpcm(samples: &[i16]) -> Vec<u8> {
+    let first = samples.first().copied().unwrap_or(0);
+    let mut out = Vec::with_capacity(4 + samples.len().div_ceil(2));
+    out.extend_from_slice(&first.to_le_bytes());
+    out.extend_from_slice(&[0, (samples.len() % 2) as u8]);
+
+    let mut predictor = first as i32;
+    let mut step_index = 0i32;
+    let mut nibbles = samples.iter().map(|sample| {
+        let step = IMA_STEP_TABLE[step_index as usize];
+        let mut diff = *sample as i32 - predictor;
+        let mut nibble = 0u8;
+        if diff < 0 {
+            nibble = 8;
+            diff = -diff;
+        }
+
+        let mut delta = step >> 3;
+        if diff >= step {
+            nibble |= 4;
+            diff -= step;
+            delta += step;
+        }
+        if diff >= step >> 1 {
+            nibble |= 2;
+            diff -= step >> 1;
+            delta += step >> 1;
+        }
+        if diff >= step >> 2 {
+            nibble |= 1;
+            delta += step >> 2;
+        }
+
+        if nibble & 8 != 0 {
+            predictor -= delta;
+        } else {
+            predictor += delta;
+        }
+        predictor = predictor.clamp(i16::MIN as i32, i16::MAX as i32);
+        step_index = (step_index + IMA_INDEX_TABLE[nibble as usize]).clamp(0, 88);
+
+        nibble
+    });
+
+    while let Some(low) = nibbles.next() {
+        let high = nibbles.next().unwrap_or(0);
+        out.push(low | (high << 4));
+    }
+
+    out
+}
+
+/// Decode a mono IMA ADPCM stream. The first four bytes hold the initial
+/// predictor (little-endian) and step index, samples follow low nibble first.
+fn decode_ima_adpcm(data: &[u8]) -> Vec<i16> {
+    if data.len() < 4 {
+        return vec![];
+    }
+
+    let mut predictor = i16::from_le_bytes([data[0], data[1]]) as i32;
+    let mut step_index = (data[2] as i32).clamp(0, 88);
+
+    let mut samples = Vec::with_capacity((data.len() - 4) * 2);
+    for byte in &data[4..] {
+        for nibble in [byte & 0xF, byte >> 4] {
+            let step = IMA_STEP_TABLE[step_index as usize];
+            let mut diff = step >> 3;
+            if nibble & 1 != 0 {
+                diff += step >> 2;
+            }
+            if nibble & 2 != 0 {
+                diff += step >> 1;
+            }
+            if nibble & 4 != 0 {
+                diff += step;
+            }
+            if nibble & 8 != 0 {
+                predictor -= diff;
+            } else {
+                predictor += diff;
+            }
+            predictor = predictor.clamp(i16::MIN as i32, i16::MAX as i32);
+            step_index = (step_index + IMA_INDEX_TABLE[nibble as usize]).clamp(0, 88);
+
+            samples.push(predictor as i16);
+        }
+    }
+    // the high nibble of the last byte is unused after an odd number of samples
+    if data[3] == 1 {
+        samples.pop();
+    }
+
+    samples
+}
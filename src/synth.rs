@@ -0,0 +1,335 @@
+use crate::builder::lay_out;
+use crate::file::db_terminator;
+use crate::format::{
+    encode_old_model, padding_block, terminator_for_component, trailer_length, GpsDbCountry,
+    RDModel, SOUND_DB_KEY,
+};
+
+/// A component of a synthetic BLOB.
+#[derive(Clone)]
+pub struct ComponentSpec {
+    /// Component name, as in [`crate::file::FWFileKind::to_name`]
+    pub name: &'static str,
+    /// Declared length, including the 12-byte trailer block of sound and GPS DBs
+    pub length: usize,
+    pub version: i32,
+    /// Model bits of the trailer word, defaulting to the model of the BLOB
+    pub model: Option<RDModel>,
+    /// Byte padded sections are filled up with
+    pub fill: u8,
+    /// Country of GPS DBs, picking the old encoding where there is one
+    pub country: GpsDbCountry,
+    /// POI count stored in the trailer block of GPS DBs
+    pub poi: i32,
+}
+
+impl ComponentSpec {
+    pub fn new(name: &'static str, length: usize, version: i32) -> ComponentSpec {
+        ComponentSpec {
+            name,
+            length,
+            version,
+            model: None,
+            fill: 0xFF,
+            country: GpsDbCountry::Us,
+            poi: 0,
+        }
+    }
+
+    pub fn model(mut self, model: RDModel) -> ComponentSpec {
+        self.model = Some(model);
+        self
+    }
+
+    pub fn fill(mut self, fill: u8) -> ComponentSpec {
+        self.fill = fill;
+        self
+    }
+
+    pub fn gps_db(mut self, country: GpsDbCountry, poi: i32) -> ComponentSpec {
+        self.country = country;
+        self.poi = poi;
+        self
+    }
+}
+
+/// Description of a synthetic BLOB, built by [`BlobSpec::build`].
+///
+/// Header components (ui_nu, dsp_nu, gps_nu and sound_dbnu) are placed in
+/// header order, the others follow as tagged sections in the order given.
+#[derive(Clone)]
+pub struct BlobSpec {
+    pub model: RDModel,
+    /// Start with the header describing the legacy sections
+    pub header: bool,
+    /// Close the BLOB with an `NMGF` record holding this format version
+    pub format_version: Option<i32>,
+    pub components: Vec<ComponentSpec>,
+}
+
+/// Where a component of a synthetic BLOB ended up.
+pub struct SynthComponent {
+    pub name: &'static str,
+    /// Start of the payload
+    pub offset: usize,
+    /// Length of the payload including its padding
+    pub length: usize,
+    pub declared_length: usize,
+    /// Start of the trailer word, or of the 12-byte trailer block of DBs
+    pub trailer_offset: usize,
+    /// End of the terminator
+    pub end: usize,
+    /// Position of the declared length in the header or section tag
+    pub length_field: usize,
+    /// Start of the section tag, if the component isn't described by the header
+    pub tag_offset: Option<usize>,
+}
+
+pub struct SynthBlob {
+    pub data: Vec<u8>,
+    pub components: Vec<SynthComponent>,
+}
+
+impl SynthBlob {
+    pub fn component(&self, name: &str) -> Option<&SynthComponent> {
+        self.components
+            .iter()
+            .find(|component| component.name == name)
+    }
+}
+
+/// Payload bytes of component `name`, distinct between components.
+pub fn payload(name: &str, length: usize) -> Vec<u8> {
+    let seed = name
+        .bytes()
+        .fold(0u8, |acc, b| acc.wrapping_mul(31).wrapping_add(b));
+    (0..length)
+        .map(|i| seed.wrapping_add((i % 251) as u8))
+        .collect()
+}
+
+fn model_word(model: RDModel, version: i32) -> [u8; 2] {
+    let word = (((model as u16) & 0x3F) << 10) | (version as u16 & 0x3FF);
+    word.to_le_bytes()
+}
+
+fn trailer_block(component: &ComponentSpec) -> Vec<u8> {
+    let mut block = vec![];
+    if component.name.starts_with("gps_db") {
+        match component.country.old_enc_key() {
+            Some((key, identify)) => {
+                block.extend(encode_old_model(key, &component.poi.to_le_bytes(), 0, 4));
+                block.extend(component.version.to_le_bytes());
+                block.extend(identify.as_bytes());
+            }
+            None => {
+                block.extend(component.poi.to_le_bytes());
+                block.extend(component.version.to_le_bytes());
+                block.extend(format!("AE{}", component.country.to_name()).as_bytes());
+            }
+        }
+    } else {
+        let word = component.version & 0x3FF;
+        block.extend(encode_old_model(SOUND_DB_KEY, &word.to_le_bytes(), 0, 4));
+        block.extend([0; 8]);
+    }
+    block
+}
+
+impl BlobSpec {
+    /// A BLOB with a header and without `NMGF` record.
+    pub fn legacy(model: RDModel) -> BlobSpec {
+        BlobSpec {
+            model,
+            header: true,
+            format_version: None,
+            components: vec![],
+        }
+    }
+
+    pub fn merged(model: RDModel, format_version: i32) -> BlobSpec {
+        BlobSpec {
+            format_version: Some(format_version),
+            ..BlobSpec::legacy(model)
+        }
+    }
+
+    /// A standalone GPS DB package, holding only `GPSD`/`GASD` sections.
+    pub fn package() -> BlobSpec {
+        BlobSpec {
+            model: RDModel::Unknown,
+            header: false,
+            format_version: None,
+            components: vec![],
+        }
+    }
+
+    pub fn with(mut self, component: ComponentSpec) -> BlobSpec {
+        self.components.push(component);
+        self
+    }
+
+    /// Lay out the header, padded payloads, trailers and tags the way the
+    /// firmware updater expects them.
+    pub fn build(&self) -> SynthBlob {
+        let sections = self
+            .components
+            .iter()
+            .map(|component| {
+                let section = self.section(component);
+                (component.name, component.length as u32, section)
+            })
+            .collect::<Vec<_>>();
+        let (data, offsets) = lay_out(&sections, self.header, self.format_version);
+
+        let mut components = self
+            .components
+            .iter()
+            .zip(&sections)
+            .zip(offsets)
+            .filter_map(|((component, (name, _, section)), offset)| {
+                let offset = offset?;
+                let (length, trailer_offset) = match db_terminator(name) {
+                    Some(_) => (component.length, component.length.saturating_sub(12)),
+                    None => {
+                        let length = section.len() - trailer_length(name);
+                        (length, length)
+                    }
+                };
+                let (length_field, tag_offset) = match *name {
+                    "ui_nu" => (0, None),
+                    "dsp_nu" => (4, None),
+                    "gps_nu" => (8, None),
+                    "sound_dbnu" => (20, None),
+                    _ => (offset - 4, Some(offset - 12)),
+                };
+                Some(SynthComponent {
+                    name,
+                    offset,
+                    length,
+                    declared_length: component.length,
+                    trailer_offset: offset + trailer_offset,
+                    end: offset + section.len(),
+                    length_field,
+                    tag_offset,
+                })
+            })
+            .collect::<Vec<_>>();
+        components.sort_by_key(|component| component.offset);
+        SynthBlob { data, components }
+    }
+
+    /// Payload, padding, trailer and terminator of `component`.
+    fn section(&self, component: &ComponentSpec) -> Vec<u8> {
+        let name = component.name;
+        let mut data = vec![];
+        match db_terminator(name) {
+            Some((skip, _)) => {
+                let body = component.length.saturating_sub(12);
+                data.extend(payload(name, body));
+                data.extend(trailer_block(component));
+                data.extend(vec![0; skip]);
+            }
+            None => {
+                let block = padding_block(name).unwrap() as usize;
+                data.extend(payload(name, component.length));
+                data.resize((component.length / block + 1) * block, component.fill);
+                let model = component.model.unwrap_or(self.model);
+                data.extend(model_word(model, component.version));
+            }
+        }
+        data.extend(terminator_for_component(name).unwrap().as_bytes());
+        data
+    }
+}
+
+/// A structure-aware change to a synthetic BLOB, for fuzzing the parser.
+///
+/// Components are picked by index, wrapping around, and changes falling
+/// outside of the BLOB are ignored, so that any mutation can be applied.
+#[derive(Clone, Debug)]
+pub enum Mutation {
+    /// Overwrite the declared length of a component
+    Length { component: usize, length: i32 },
+    /// Overwrite the section tag of a component, if it has one
+    Tag { component: usize, tag: [u8; 4] },
+    /// Overwrite a byte of the trailer of a component
+    Trailer {
+        component: usize,
+        index: usize,
+        value: u8,
+    },
+    /// Set or clear the sound DB flag in the header
+    SoundDbFlag(bool),
+    /// Cut the BLOB short
+    Truncate(usize),
+    /// Insert bytes, shifting everything after them
+    Insert { offset: usize, bytes: Vec<u8> },
+    /// Overwrite any byte
+    Byte { offset: usize, value: u8 },
+}
+
+impl SynthBlob {
+    fn pick(&self, component: usize) -> Option<&SynthComponent> {
+        if self.components.is_empty() {
+            None
+        } else {
+            self.components.get(component % self.components.len())
+        }
+    }
+
+    fn write(&mut self, offset: usize, bytes: &[u8]) {
+        if let Some(target) = offset
+            .checked_add(bytes.len())
+            .and_then(|end| self.data.get_mut(offset..end))
+        {
+            target.copy_from_slice(bytes);
+        }
+    }
+
+    /// Apply `mutation` to the data of the BLOB. The component layout is left
+    /// as built, so later mutations still aim at the original structure.
+    pub fn mutate(&mut self, mutation: &Mutation) {
+        match mutation {
+            Mutation::Length { component, length } => {
+                let Some(component) = self.pick(*component) else {
+                    return;
+                };
+                let (field, bytes) = (component.length_field, length.to_le_bytes());
+                if component.name == "ui_nu" {
+                    // the upper byte holds the sound DB flag
+                    self.write(field, &bytes[..3]);
+                } else {
+                    self.write(field, &bytes);
+                }
+            }
+            Mutation::Tag { component, tag } => {
+                if let Some(offset) = self.pick(*component).and_then(|c| c.tag_offset) {
+                    self.write(offset, tag);
+                }
+            }
+            Mutation::Trailer {
+                component,
+                index,
+                value,
+            } => {
+                let Some(component) = self.pick(*component) else {
+                    return;
+                };
+                let span = component.end - component.trailer_offset;
+                self.write(component.trailer_offset + index % span, &[*value]);
+            }
+            Mutation::SoundDbFlag(set) => {
+                if let Some(flags) = self.data.get_mut(3) {
+                    *flags = (*flags & !1) | *set as u8;
+                }
+            }
+            Mutation::Truncate(length) => self.data.truncate(*length),
+            Mutation::Insert { offset, bytes } => {
+                let offset = (*offset).min(self.data.len());
+                self.data.splice(offset..offset, bytes.iter().copied());
+            }
+            Mutation::Byte { offset, value } => self.write(*offset, &[*value]),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::format::RDModel;
+use ufwt_core::trailer::Trailer as RawTrailer;
+
+/// End of a component: the model/version word of padded sections or the
+/// 12-byte trailer block of sound and GPS DBs, any skipped bytes, and the
+/// `DRSWxxx` terminator.
+///
+/// Owned copy of [`ufwt_core::trailer::Trailer`], kept by parsed components.
+#[derive(Clone)]
+pub struct Trailer {
+    pub(crate) offset: u64,
+    pub(crate) raw: Vec<u8>,
+    pub(crate) model: Option<RDModel>,
+    pub(crate) version: i32,
+    pub(crate) extra: Vec<u8>,
+    pub(crate) terminator: String,
+}
+
+impl Trailer {
+    /// Part of the trailer inside the payload of component `name`, and the
+    /// whole length of its trailer.
+    ///
+    /// The 12-byte block of sound and GPS DBs counts towards their declared
+    /// length, while the word after padded sections doesn't.
+    pub fn span(name: &str) -> (usize, usize) {
+        RawTrailer::span(name)
+    }
+
+    /// Decode the trailer of component `name` from `bytes`, which must be
+    /// exactly as long as [`Trailer::span`] says. `offset` is where it starts.
+    pub fn parse(name: &str, bytes: &[u8], offset: u64) -> Option<Trailer> {
+        let trailer = RawTrailer::parse(name, bytes, 0)?;
+        Some(Trailer {
+            offset,
+            ..Trailer::from(&trailer)
+        })
+    }
+
+    /// Read the trailer of component `name` whose payload ends at `end` in `buffer`.
+    pub fn read(buffer: &[u8], name: &str, end: usize) -> Option<Trailer> {
+        RawTrailer::read(buffer, name, end).map(|trailer| Trailer::from(&trailer))
+    }
+
+    /// Position of the trailer in the BLOB.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The trailer as stored, up to and including the terminator.
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// Model bits of padded sections; sound and GPS DBs have none.
+    pub fn model(&self) -> Option<RDModel> {
+        self.model
+    }
+
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Bytes skipped between the trailer block and the terminator of `GASD` and `SUSD`.
+    pub fn extra(&self) -> &[u8] {
+        &self.extra
+    }
+
+    pub fn terminator(&self) -> &str {
+        &self.terminator
+    }
+}
+
+impl From<&RawTrailer<'_>> for Trailer {
+    fn from(trailer: &RawTrailer) -> Trailer {
+        Trailer {
+            offset: trailer.offset as u64,
+            raw: trailer.raw.to_vec(),
+            model: trailer.model,
+            version: trailer.version,
+            extra: trailer.extra.to_vec(),
+            terminator: String::from_utf8_lossy(trailer.terminator).into_owned(),
+        }
+    }
+}
@@ -7,18 +7,13 @@ type Iv = Interval<u64, ()>;
 
 fn read_n_bytes(cursor: &mut Cursor<&Vec<u8>>, n: usize) -> io::Result<Vec<u8>> {
     let mut buffer = vec![0; n];
-    cursor.read(&mut buffer)?;
+    cursor.read_exact(&mut buffer)?;
     Ok(buffer)
 }
 
-#[inline(always)]
-pub(crate) fn alter_length(length: i32) -> i32 {
-    // can also be written as (length & 0xfffffe00) + 512
-    if length != 0 {
-        (length / 512 + 1) * 512
-    } else {
-        length
-    }
+/// Lowercase hex encoding of `bytes`, without separators.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
 pub trait CursorHelper {
@@ -37,7 +32,7 @@ impl<'a> TrackingCursor<'a> {
     pub fn new(data: &'a Vec<u8>, read_intervals: &'a mut Vec<Iv>) -> Self {
         TrackingCursor {
             cursor: Cursor::new(data),
-            read_intervals: read_intervals,
+            read_intervals,
         }
     }
 }
@@ -83,7 +78,7 @@ impl CursorHelper for TrackingCursor<'_> {
 
     fn seek(&mut self, n: u64) {
         let pos = self.position();
-        self.set_position(pos + n)
+        self.set_position(pos.saturating_add(n))
     }
 
     fn seek_set(&mut self, n: u64) {
@@ -0,0 +1,78 @@
+use crate::file::{handle_gpsdb_file_info, ExtractMode, FWFile, FWFileKind, UnidenFirmware};
+use crate::format::{decode_old_model, GpsDbType, SECTION_TAGS, SOUND_DB_KEY};
+use crate::trailer::Trailer;
+
+/// A component of a parsed BLOB, borrowing its bytes instead of copying them.
+#[derive(Clone, Copy)]
+pub struct ComponentView<'a> {
+    firmware: &'a UnidenFirmware,
+    file: &'a FWFile,
+}
+
+impl<'a> ComponentView<'a> {
+    pub fn file(&self) -> &'a FWFile {
+        self.file
+    }
+
+    pub fn kind(&self) -> &'a FWFileKind {
+        self.file.kind()
+    }
+
+    /// Section tag, `None` for the sections described by the header.
+    pub fn tag(&self) -> Option<&'static str> {
+        let name = self.kind().to_name();
+        SECTION_TAGS
+            .iter()
+            .find(|(_, n, _)| *n == name)
+            .map(|(tag, _, _)| *tag)
+    }
+
+    pub fn version(&self) -> i32 {
+        self.file.info().base().version()
+    }
+
+    /// The payload including its padding, and the trailer block of DBs.
+    pub fn payload(&self) -> &'a [u8] {
+        self.slice(ExtractMode::Padded)
+    }
+
+    /// The part of the component written by [`UnidenFirmware::extract_to`] in `mode`.
+    pub fn slice(&self, mode: ExtractMode) -> &'a [u8] {
+        self.firmware.file_slice(self.file, mode)
+    }
+
+    pub fn trailer(&self) -> Option<&'a Trailer> {
+        self.file.trailer()
+    }
+
+    /// The body of sound DBs and old encoding GPS DBs with their encoding undone,
+    /// leaving out the trailer block. `None` for the other components, including
+    /// AES-128 GPS DBs, whose encoding isn't known.
+    pub fn decoded(&self) -> Option<Vec<u8>> {
+        let key = match self.kind() {
+            FWFileKind::SoundDbnu(_) | FWFileKind::SoundDbla1(_) | FWFileKind::SoundDbla2(_) => {
+                SOUND_DB_KEY
+            }
+            kind => {
+                let info = handle_gpsdb_file_info(kind)?;
+                if !matches!(info.file_type(), GpsDbType::GpsDbOldEnc) {
+                    return None;
+                }
+                info.country()?.old_enc_key()?.0
+            }
+        };
+        let payload = self.payload();
+        let body = payload.len().checked_sub(12)? & !3;
+        Some(decode_old_model(key, payload, 0, body))
+    }
+}
+
+impl UnidenFirmware {
+    /// Components found by [`UnidenFirmware::read_buffer`], in BLOB order.
+    pub fn components(&self) -> impl Iterator<Item = ComponentView<'_>> {
+        self.files.iter().map(move |file| ComponentView {
+            firmware: self,
+            file,
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use ufwt::builder::FirmwareBuilder;
+use ufwt::file::{ExtractMode, FWFileKind, UnidenFirmware};
+use ufwt::format::{GpsDbCountry, GpsDbType, RDModel};
+use ufwt::gpsdb::{CameraType, GpsDb, Poi};
+use ufwt::synth::payload;
+
+fn parse(data: &[u8]) -> UnidenFirmware {
+    let mut firmware = UnidenFirmware::from_bytes(data);
+    firmware.read_buffer().unwrap();
+    firmware
+}
+
+#[test]
+fn builds_blob_from_components() {
+    let pois = vec![
+        Poi {
+            latitude: 40.5,
+            longitude: -74.25,
+            camera: CameraType::RedLight,
+            speed: 0,
+            heading: None,
+        },
+        Poi {
+            latitude: 41.0,
+            longitude: -73.5,
+            camera: CameraType::Speed,
+            speed: 55,
+            heading: Some(90),
+        },
+    ];
+    let blob = FirmwareBuilder::new()
+        .model(RDModel::R8)
+        .format_version(3)
+        .component(FWFileKind::DspStm, &payload("dsp_stm", 700), 20)
+        .component(FWFileKind::UiNu, &payload("ui_nu", 1000), 11)
+        .component(FWFileKind::SoundDbnu, &payload("sound_dbnu", 40), 7)
+        .gps_db(GpsDbCountry::Us, GpsDbType::GpsDbOldEnc, pois.clone(), 123)
+        .build()
+        .unwrap();
+    let firmware = parse(&blob);
+
+    let metadata = firmware.metadata.as_ref().unwrap();
+    assert!(metadata.model == RDModel::R8);
+    assert_eq!(metadata.format_version, Some(3));
+    // the sound DB flag in the upper byte of the first header word
+    assert_eq!(blob[3], 1);
+
+    let views: Vec<_> = firmware.components().collect();
+    let names: Vec<_> = views.iter().map(|view| view.kind().to_name()).collect();
+    assert_eq!(names, ["ui_nu", "sound_dbnu", "dsp_stm", "gps_db"]);
+    let versions: Vec<_> = views.iter().map(|view| view.version()).collect();
+    assert_eq!(versions, [11, 7, 20, 123]);
+    assert_eq!(
+        views[2].slice(ExtractMode::Trimmed),
+        payload("dsp_stm", 700)
+    );
+    assert_eq!(views[2].payload().len(), 1024);
+    assert!(views[2].trailer().unwrap().model() == Some(RDModel::R8));
+
+    let gps_db = GpsDb::parse(views[3].payload()).unwrap();
+    assert_eq!(gps_db.pois.len(), 2);
+    assert_eq!(gps_db.pois[1].speed, 55);
+
+    let error = FirmwareBuilder::new()
+        .model(RDModel::R8)
+        .gps_db(GpsDbCountry::Eu, GpsDbType::GpsDbAes128, pois, 1)
+        .build()
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
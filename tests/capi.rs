@@ -0,0 +1,76 @@
+use std::ffi::CStr;
+use std::{mem, ptr, slice};
+
+use ufwt::capi::{
+    ufwt_component, ufwt_component_count, ufwt_component_data, ufwt_free, ufwt_last_error,
+    ufwt_open, UfwtComponent,
+};
+use ufwt::format::{GpsDbCountry, RDModel};
+use ufwt::synth::{BlobSpec, ComponentSpec};
+
+#[test]
+fn enumerates_components_through_c_api() {
+    let blob = BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("sound_dbnu", 112, 7))
+        .with(ComponentSpec::new("ble", 100, 21))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Nz, 4))
+        .build();
+
+    unsafe {
+        let firmware = ufwt_open(blob.data.as_ptr(), blob.data.len(), false);
+        assert!(!firmware.is_null());
+        assert_eq!(ufwt_component_count(firmware), blob.components.len());
+
+        let mut component: UfwtComponent = mem::zeroed();
+        for (index, synth) in blob.components.iter().enumerate() {
+            assert!(ufwt_component(firmware, index, &mut component));
+            assert_eq!(CStr::from_ptr(component.kind).to_str(), Ok(synth.name));
+            assert_eq!(component.offset as usize, synth.offset);
+            assert_eq!(component.length as usize, synth.length);
+            assert_eq!(component.declared_length as usize, synth.declared_length);
+            assert!(!component.damaged);
+
+            let mut length = 0;
+            let data = ufwt_component_data(firmware, index, &mut length);
+            assert_eq!(length, synth.declared_length.min(synth.length));
+            assert_eq!(
+                slice::from_raw_parts(data, length),
+                &blob.data[synth.offset..synth.offset + length]
+            );
+        }
+        assert!(ufwt_component(firmware, 0, &mut component));
+        assert!(component.tag.is_null());
+        assert_eq!(component.version, 11);
+        assert!(ufwt_component(firmware, 2, &mut component));
+        assert_eq!(CStr::from_ptr(component.tag).to_str(), Ok("BLES"));
+
+        let count = blob.components.len();
+        assert!(!ufwt_component(firmware, count, &mut component));
+        assert!(ufwt_component_data(firmware, count, ptr::null_mut()).is_null());
+        ufwt_free(firmware);
+
+        let truncated = &blob.data[..30];
+        assert!(ufwt_open(truncated.as_ptr(), truncated.len(), false).is_null());
+        assert!(!CStr::from_ptr(ufwt_last_error()).is_empty());
+        assert_eq!(ufwt_component_count(ptr::null()), 0);
+        ufwt_free(ptr::null_mut());
+    }
+}
+
+#[test]
+fn clears_last_error_on_success() {
+    let blob = BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .build();
+
+    unsafe {
+        assert!(ufwt_open(blob.data.as_ptr(), 30, false).is_null());
+        assert!(!ufwt_last_error().is_null());
+
+        let firmware = ufwt_open(blob.data.as_ptr(), blob.data.len(), false);
+        assert!(!firmware.is_null());
+        assert!(ufwt_last_error().is_null());
+        ufwt_free(firmware);
+    }
+}
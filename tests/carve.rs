@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ufwt::carve::{carve, write_sections, CarveMethod, CarvedSection};
+use ufwt::format::{GpsDbCountry, RDModel};
+use ufwt::synth::{BlobSpec, ComponentSpec, SynthBlob};
+
+fn merged() -> SynthBlob {
+    BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("dsp_nu", 700, 12))
+        .with(ComponentSpec::new("sound_dbnu", 112, 7))
+        .with(ComponentSpec::new("ble", 100, 21))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Us, 4))
+        .with(ComponentSpec::new("sound_dbla2", 52, 5))
+        .build()
+}
+
+/// Check each carved section against the BLOB it was taken from, `shift` bytes
+/// into the dump, along with how it was found and its confidence.
+fn check(
+    sections: &[CarvedSection],
+    blob: &SynthBlob,
+    shift: usize,
+    expected: &[(&str, CarveMethod, f32)],
+) {
+    let found: Vec<_> = sections
+        .iter()
+        .map(|section| (section.name, section.method))
+        .collect();
+    let wanted: Vec<_> = expected
+        .iter()
+        .map(|(name, method, _)| (*name, *method))
+        .collect();
+    assert_eq!(found, wanted);
+
+    for (section, (name, _, confidence)) in sections.iter().zip(expected) {
+        let synth = blob.component(name).unwrap();
+        assert_eq!(section.start, synth.offset + shift, "{}", name);
+        assert!(
+            (section.confidence - confidence).abs() < 1e-6,
+            "{}: {}",
+            name,
+            section.confidence
+        );
+        if section.method != CarveMethod::Unterminated {
+            assert_eq!(section.end, synth.offset + synth.length + shift, "{}", name);
+            assert_eq!(section.trailer_end, synth.end + shift, "{}", name);
+            assert_eq!(section.version, Some(synth_version(name)));
+        }
+    }
+}
+
+fn synth_version(name: &str) -> i32 {
+    match name {
+        "ui_nu" => 11,
+        "dsp_nu" => 12,
+        "sound_dbnu" => 7,
+        "ble" => 21,
+        "gps_db" => 123,
+        _ => 5,
+    }
+}
+
+#[test]
+fn carves_clean_blob() {
+    let blob = merged();
+    let sections = carve(&blob.data);
+    check(
+        &sections,
+        &blob,
+        0,
+        &[
+            // after the 24-byte header, padding counted back from the terminator
+            ("ui_nu", CarveMethod::Aligned, 0.75),
+            ("dsp_nu", CarveMethod::Aligned, 0.75),
+            ("sound_dbnu", CarveMethod::SectionOrder, 0.7),
+            ("ble", CarveMethod::Tag, 1.0),
+            ("gps_db", CarveMethod::Tag, 0.95),
+            ("sound_dbla2", CarveMethod::Tag, 0.95),
+        ],
+    );
+    assert!(sections[0].model == Some(RDModel::R8));
+    assert!(sections[4].model.is_none());
+}
+
+#[test]
+fn carves_truncated_blob() {
+    let blob = merged();
+    let gps_db = blob.component("gps_db").unwrap();
+    let cut = gps_db.offset + 40;
+    let sections = carve(&blob.data[..cut]);
+    check(
+        &sections,
+        &blob,
+        0,
+        &[
+            ("ui_nu", CarveMethod::Aligned, 0.75),
+            ("dsp_nu", CarveMethod::Aligned, 0.75),
+            ("sound_dbnu", CarveMethod::SectionOrder, 0.7),
+            ("ble", CarveMethod::Tag, 1.0),
+            // the tag survived but the section was cut off
+            ("gps_db", CarveMethod::Unterminated, 0.2),
+        ],
+    );
+    assert_eq!(sections[4].end, cut);
+    assert_eq!(sections[4].version, None);
+}
+
+#[test]
+fn carves_blob_after_junk() {
+    let blob = merged();
+    let mut data = vec![0x5A; 100];
+    data.extend_from_slice(&blob.data);
+    check(
+        &carve(&data),
+        &blob,
+        100,
+        &[
+            // the bytes before ui_nu are no longer a BLOB header
+            ("ui_nu", CarveMethod::Aligned, 0.55),
+            ("dsp_nu", CarveMethod::Aligned, 0.75),
+            ("sound_dbnu", CarveMethod::SectionOrder, 0.7),
+            ("ble", CarveMethod::Tag, 1.0),
+            ("gps_db", CarveMethod::Tag, 0.95),
+            ("sound_dbla2", CarveMethod::Tag, 0.95),
+        ],
+    );
+}
+
+#[test]
+fn ignores_tags_after_the_trailer_block() {
+    // a GASD tag overlapping the trailer block of the GPS DB it names
+    let mut data = vec![0; 20];
+    data.extend_from_slice(b"GASD\0\0LRDB\0\0");
+    data.extend_from_slice(b"DRSWGAE");
+    data.extend_from_slice(&[0; 16]);
+
+    let sections = carve(&data);
+    assert_eq!(sections.len(), 1);
+    let section = &sections[0];
+    assert_eq!(section.name, "gps_db_second");
+    assert_eq!((section.start, section.end), (0, 30));
+    assert_eq!(section.method, CarveMethod::Fallback);
+
+    let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("carve");
+    write_sections(&data, &sections, &directory).unwrap();
+    assert_eq!(
+        fs::read(directory.join("00000000_gps_db_second.bin")).unwrap(),
+        data[..30]
+    );
+
+    let reversed = CarvedSection {
+        name: "gps_db_second",
+        start: 32,
+        end: 30,
+        trailer_end: 39,
+        version: None,
+        model: None,
+        method: CarveMethod::Fallback,
+        confidence: 0.3,
+    };
+    let error = write_sections(&data, &[reversed], &directory).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid bounds for gps_db_second: 0x20-0x1e"
+    );
+}
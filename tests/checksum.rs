@@ -0,0 +1,220 @@
+use ufwt::builder::FirmwareBuilder;
+use ufwt::checksum::{
+    find_checksums, find_in_corpus, Algorithm, Candidate, Coverage, Location, Transform,
+};
+use ufwt::file::{ExtractMode, FWFileKind, UnidenFirmware};
+use ufwt::format::RDModel;
+use ufwt::synth::{BlobSpec, ComponentSpec};
+
+fn parse(data: &[u8]) -> UnidenFirmware {
+    let mut firmware = UnidenFirmware::from_bytes(data);
+    firmware.read_buffer().unwrap();
+    firmware
+}
+
+/// Pseudo-random bytes, so that checksums don't match by accident.
+fn noise(seed: u32, length: usize) -> Vec<u8> {
+    let mut state = seed;
+    (0..length)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn computes_checksum_check_values() {
+    // the CRCs use the check values of the usual catalogues
+    let checks = [
+        (Algorithm::Crc16CcittFalse, 0x29B1),
+        (Algorithm::Crc16Xmodem, 0x31C3),
+        (Algorithm::Crc16Arc, 0xBB3D),
+        (Algorithm::Crc16Modbus, 0x4B37),
+        (Algorithm::Crc16Kermit, 0x2189),
+        (Algorithm::Crc32, 0xCBF43926),
+        (Algorithm::Crc32Mpeg2, 0x0376E6E7),
+        (Algorithm::Crc32Bzip2, 0xFC891918),
+        (Algorithm::Sum8, 0xDD),
+        (Algorithm::Sum16, 0x01DD),
+        (Algorithm::Sum32, 0x01DD),
+        (Algorithm::Sum16Words, 0xD509),
+        (Algorithm::Sum32Words, 0x6C6A689F),
+        (Algorithm::Xor8, 0x31),
+        (Algorithm::Xor32Words, 0x0C04043D),
+        (Algorithm::Fletcher16, 0x1EDE),
+        (Algorithm::Fletcher32, 0xDF09D509),
+    ];
+    assert_eq!(checks.len(), Algorithm::ALL.len());
+    for (algorithm, check) in checks {
+        assert_eq!(
+            algorithm.compute(b"123456789"),
+            check,
+            "{}",
+            algorithm.to_name()
+        );
+    }
+    assert_eq!(Algorithm::Crc16CcittFalse.compute(b""), 0xFFFF);
+    assert_eq!(Algorithm::Crc32Bzip2.compute(b""), 0);
+
+    for (data, fletcher16, fletcher32) in [
+        (&b"abcde"[..], 0xC8F0, 0xF04FC729),
+        (b"abcdef", 0x2057, 0x56502D2A),
+        (b"abcdefgh", 0x0627, 0xEBE19591),
+    ] {
+        assert_eq!(Algorithm::Fletcher16.compute(data), fletcher16);
+        assert_eq!(Algorithm::Fletcher32.compute(data), fletcher32);
+    }
+}
+
+#[test]
+fn finds_negated_and_inverted_checksums() {
+    // ui_nu starts with the inverted sum32 of the rest of its body, big endian
+    let mut ui = noise(1, 1000);
+    let sum = Algorithm::Sum32.compute(&ui[4..]);
+    ui[..4].copy_from_slice(&(!sum).to_be_bytes());
+    // dsp_nu ends with the negated sum16/words of its body, little endian
+    let mut dsp = noise(2, 700);
+    let sum = Algorithm::Sum16Words.compute(&dsp) as u16;
+    dsp.extend_from_slice(&sum.wrapping_neg().to_le_bytes());
+    assert_eq!(Algorithm::Sum16Words.compute(&dsp), 0);
+
+    let blob = FirmwareBuilder::new()
+        .model(RDModel::R8)
+        .component(FWFileKind::UiNu, &ui, 11)
+        .component(FWFileKind::DspNu, &dsp, 12)
+        .build()
+        .unwrap();
+    let found = find_checksums(&parse(&blob));
+    let inverted = Candidate {
+        component: "ui_nu",
+        algorithm: Algorithm::Sum32,
+        location: Location::BodyStart,
+        coverage: Coverage::Body,
+        big_endian: true,
+        transform: Transform::Inverted,
+    };
+    let negated = Candidate {
+        component: "dsp_nu",
+        algorithm: Algorithm::Sum16Words,
+        location: Location::BodyEnd,
+        coverage: Coverage::Body,
+        big_endian: false,
+        transform: Transform::Negated,
+    };
+    assert!(found.contains(&inverted));
+    assert!(found.contains(&negated));
+    assert_eq!(
+        inverted.describe(),
+        "ui_nu: inverted sum32 (big endian) at body_start over body"
+    );
+    // neither is mistaken for the plain or other transformed sum
+    for transform in [Transform::Plain, Transform::Negated] {
+        assert!(!found.contains(&Candidate {
+            transform,
+            ..inverted
+        }));
+    }
+    for transform in [Transform::Plain, Transform::Inverted] {
+        assert!(!found.contains(&Candidate {
+            transform,
+            ..negated
+        }));
+    }
+}
+
+#[test]
+fn finds_embedded_checksums() {
+    let blobs: Vec<_> = (1..4)
+        .map(|seed| {
+            let mut dsp = noise(seed, 700 + seed as usize);
+            dsp.extend_from_slice(&crc32fast::hash(&dsp).to_be_bytes());
+            let mut ui = noise(seed + 10, 1000);
+            let sum = ui[2..]
+                .iter()
+                .fold(0u16, |sum, &b| sum.wrapping_add(b as u16));
+            ui[..2].copy_from_slice(&sum.wrapping_neg().to_le_bytes());
+            let blob = FirmwareBuilder::new()
+                .model(RDModel::R8)
+                .component(FWFileKind::UiNu, &ui, 11)
+                .component(FWFileKind::DspNu, &dsp, 12)
+                .component(FWFileKind::UiStm, &noise(seed + 20, 600), 20)
+                .build()
+                .unwrap();
+            parse(&blob)
+        })
+        .collect();
+
+    let dsp = Candidate {
+        component: "dsp_nu",
+        algorithm: Algorithm::Crc32,
+        location: Location::BodyEnd,
+        coverage: Coverage::Body,
+        big_endian: true,
+        transform: Transform::Plain,
+    };
+    assert_eq!(
+        dsp.describe(),
+        "dsp_nu: crc32 (big endian) at body_end over body"
+    );
+    assert!(find_checksums(&blobs[0]).contains(&dsp));
+
+    let consistent: Vec<_> = find_in_corpus(&blobs)
+        .into_iter()
+        .filter(|found| found.is_consistent() && found.blobs == 3)
+        .map(|found| found.candidate.describe())
+        .collect();
+    assert_eq!(
+        consistent,
+        [
+            "dsp_nu: crc32 (big endian) at body_end over body",
+            "ui_nu: negated sum16 (little endian) at body_start over body",
+        ]
+    );
+}
+
+#[test]
+fn finds_checksums_in_header() {
+    let mut blob = BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("gps_nu", 300, 13))
+        .with(ComponentSpec::new("sound_dbnu", 112, 7))
+        .build()
+        .data;
+    let firmware = parse(&blob);
+    let body = |name: &str| {
+        let file = firmware.find_file(name).unwrap();
+        firmware.file_slice(file, ExtractMode::Trimmed).to_vec()
+    };
+    // the 8 bytes between the GPS and sound DB lengths are zero and unused
+    assert_eq!(blob[12..20], [0; 8]);
+    let crc = Algorithm::Crc32.compute(&body("ui_nu"));
+    blob[12..16].copy_from_slice(&crc.to_le_bytes());
+    let sum = Algorithm::Sum16.compute(&body("gps_nu")) as u16;
+    blob[18..20].copy_from_slice(&sum.to_be_bytes());
+
+    let found = find_checksums(&parse(&blob));
+    let start = Candidate {
+        component: "ui_nu",
+        algorithm: Algorithm::Crc32,
+        location: Location::HeaderSpareStart,
+        coverage: Coverage::Body,
+        big_endian: false,
+        transform: Transform::Plain,
+    };
+    let end = Candidate {
+        component: "gps_nu",
+        algorithm: Algorithm::Sum16,
+        location: Location::HeaderSpareEnd,
+        big_endian: true,
+        ..start
+    };
+    assert!(found.contains(&start));
+    assert!(found.contains(&end));
+    assert_eq!(
+        end.describe(),
+        "gps_nu: sum16 (big endian) at header_spare_end over body"
+    );
+}
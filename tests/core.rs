@@ -0,0 +1,98 @@
+use ufwt::format::{GpsDbCountry, RDModel};
+use ufwt::synth::{BlobSpec, ComponentSpec, Mutation};
+use ufwt_core::format::{decode_old_model, decode_old_model_into, encode_old_model_into};
+use ufwt_core::section::{FormatGeneration, Sections};
+use ufwt_core::Error;
+
+fn spec() -> BlobSpec {
+    BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("gps_nu", 300, 13))
+        .with(ComponentSpec::new("sound_dbnu", 112, 7))
+        .with(ComponentSpec::new("ble", 100, 21))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Nz, 4))
+        .with(ComponentSpec::new("sound_dbla2", 52, 5))
+}
+
+#[test]
+fn iterates_sections_on_borrowed_slice() {
+    let blob = spec().build();
+    let mut sections = Sections::new(&blob.data);
+    assert_eq!(sections.generation(), FormatGeneration::Merged);
+    assert_eq!(sections.format_version(), Some(3));
+
+    let mut read = vec![];
+    let mut found = vec![];
+    while let Some(section) = sections.next_with(&mut |start, end| read.push((start, end))) {
+        found.push(section.unwrap());
+    }
+    assert_eq!(found.len(), blob.components.len());
+    for (section, synth) in found.iter().zip(&blob.components) {
+        assert_eq!(section.name, synth.name);
+        assert_eq!(section.offset, synth.offset);
+        assert_eq!(section.payload.len(), synth.length);
+        assert_eq!(section.declared_length as usize, synth.declared_length);
+        assert_eq!(section.tag_offset, synth.tag_offset);
+        assert_eq!(section.trailer.offset, synth.trailer_offset);
+        assert_eq!(
+            section.trailer.raw,
+            &blob.data[synth.trailer_offset..synth.end]
+        );
+    }
+    assert_eq!(found[4].gps_db.unwrap().poi, 4);
+    assert!(found[0].trailer.model == Some(RDModel::R8));
+    assert_eq!(read.first(), Some(&(0, 24)));
+    assert_eq!(read.last(), Some(&(blob.data.len() - 12, blob.data.len())));
+}
+
+#[test]
+fn stops_after_error_until_seek() {
+    let mut blob = spec().build();
+    let ble = blob.component("ble").unwrap();
+    let (terminator, offset) = (ble.end - 7, ble.offset);
+    blob.mutate(&Mutation::Byte {
+        offset: terminator,
+        value: b'X',
+    });
+
+    let mut sections = Sections::new(&blob.data);
+    let names: Vec<_> = sections
+        .by_ref()
+        .map_while(Result::ok)
+        .map(|s| s.name)
+        .collect();
+    assert_eq!(names, ["ui_nu", "gps_nu", "sound_dbnu"]);
+    assert!(sections.next().is_none());
+
+    let mut sections = Sections::new(&blob.data);
+    let error = sections.find_map(Result::err).unwrap();
+    assert_eq!(
+        error,
+        Error::WrongTerminator {
+            offset,
+            name: "ble",
+            terminator,
+            found: *b"XRSWBLE",
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "Wrong termination sequence after ble: XRSWBLE"
+    );
+
+    sections.seek(terminator + 7);
+    let names: Vec<_> = sections.map(|s| s.unwrap().name).collect();
+    assert_eq!(names, ["gps_db", "sound_dbla2"]);
+}
+
+#[test]
+fn decodes_without_allocating() {
+    let data: Vec<u8> = (0..16).map(|i| i * 17).collect();
+    let mut encoded = [0; 16];
+    encode_old_model_into(210, &data, &mut encoded);
+
+    let mut decoded = [0; 16];
+    decode_old_model_into(210, &encoded, &mut decoded);
+    assert_eq!(decoded.as_slice(), data);
+    assert_eq!(decode_old_model(210, &encoded, 0, 16), data);
+}
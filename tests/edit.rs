@@ -0,0 +1,241 @@
+use ufwt::file::{ExtractMode, UnidenFirmware};
+use ufwt::format::{GpsDbCountry, RDModel};
+use ufwt::synth::{BlobSpec, ComponentSpec};
+
+fn parse(data: &[u8]) -> UnidenFirmware {
+    let mut firmware = UnidenFirmware::from_bytes(data);
+    firmware.read_buffer().unwrap();
+    firmware
+}
+
+fn blob() -> Vec<u8> {
+    BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("dsp_nu", 700, 12))
+        .with(ComponentSpec::new("sound_dbnu", 112, 7))
+        .with(ComponentSpec::new("ble", 100, 21))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Us, 4))
+        .with(ComponentSpec::new("sound_dbla2", 52, 5))
+        .build()
+        .data
+}
+
+fn firmware() -> UnidenFirmware {
+    parse(&blob())
+}
+
+/// Names, declared lengths and versions of the components of `firmware`.
+fn layout(firmware: &UnidenFirmware) -> Vec<(&'static str, i32, i32)> {
+    firmware
+        .files()
+        .iter()
+        .map(|file| {
+            let info = file.info().base();
+            (
+                file.kind().to_name(),
+                info.declared_length(),
+                info.version(),
+            )
+        })
+        .collect()
+}
+
+fn trimmed<'a>(firmware: &'a UnidenFirmware, name: &str) -> &'a [u8] {
+    firmware.file_slice(firmware.find_file(name).unwrap(), ExtractMode::Trimmed)
+}
+
+#[test]
+fn replaces_padded_component() {
+    let firmware = firmware();
+    let dsp: Vec<u8> = (0..1500).map(|i| (i % 7) as u8).collect();
+
+    let replaced = parse(&firmware.replace_component("dsp_nu", &dsp, None).unwrap());
+    let file = replaced.find_file("dsp_nu").unwrap();
+    assert_eq!(file.info().base().length(), 1536);
+    assert_eq!(trimmed(&replaced, "dsp_nu"), dsp);
+    assert!(replaced.file_data(file)[1500..].iter().all(|&b| b == 0xFF));
+
+    let mut expected = layout(&firmware);
+    expected[1].1 = 1500;
+    assert_eq!(layout(&replaced), expected);
+    for name in ["ui_nu", "ble", "gps_db", "sound_dbla2"] {
+        assert_eq!(trimmed(&replaced, name), trimmed(&firmware, name));
+    }
+
+    let replaced = parse(&firmware.replace_component("ble", &dsp, Some(42)).unwrap());
+    let file = replaced.find_file("ble").unwrap();
+    assert_eq!(file.info().base().length(), 2048);
+    assert_eq!(file.info().base().version(), 42);
+    assert!(file.trailer().unwrap().model() == Some(RDModel::R8));
+
+    let error = firmware
+        .replace_component("dsp_nu", &dsp, Some(0x400))
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Version 1024 does not fit in 10 bits");
+}
+
+#[test]
+fn replaces_component_with_its_own_extract() {
+    let firmware = firmware();
+    for name in ["ui_nu", "dsp_nu", "ble"] {
+        let file = firmware.find_file(name).unwrap();
+        let raw = firmware.file_slice(file, ExtractMode::Raw);
+        let error = firmware.replace_component(name, raw, None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Replacement {} ends with its trailer and terminator, give the image written by `extract --trim` instead",
+                name
+            )
+        );
+
+        let replaced = firmware
+            .replace_component(name, trimmed(&firmware, name), None)
+            .unwrap();
+        assert_eq!(replaced, blob(), "{}", name);
+    }
+}
+
+#[test]
+fn replaces_databases_with_or_without_terminator() {
+    let firmware = firmware();
+    for (name, suffix) in [
+        ("sound_dbnu", &b"DRSWSDB"[..]),
+        ("sound_dbla2", &b"\0\0DRSWSUS"[..]),
+        ("gps_db", &b"DRSWGDB"[..]),
+    ] {
+        // a longer body followed by the original trailer block
+        let original = trimmed(&firmware, name);
+        let mut db = vec![0x5A; 40];
+        db.extend_from_slice(original);
+
+        let bare = firmware.replace_component(name, &db, None).unwrap();
+        let mut terminated = db.clone();
+        terminated.extend_from_slice(suffix);
+        assert_eq!(
+            firmware.replace_component(name, &terminated, None).unwrap(),
+            bare
+        );
+
+        let replaced = parse(&bare);
+        let file = replaced.find_file(name).unwrap();
+        assert_eq!(file.info().base().declared_length() as usize, db.len());
+        assert_eq!(file.info().base().length() as usize, db.len());
+        assert_eq!(trimmed(&replaced, name), db);
+        assert_eq!(
+            file.info().base().version(),
+            firmware.find_file(name).unwrap().info().base().version()
+        );
+    }
+
+    let error = firmware
+        .replace_component("sound_dbnu", &[0; 11], None)
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "sound_dbnu must include its 12-byte trailer block"
+    );
+    let error = firmware
+        .replace_component("gps_db", &[0; 40], Some(3))
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "The version of gps_db is stored in its encoded trailer block"
+    );
+}
+
+#[test]
+fn limits_ui_nu_to_24_bits() {
+    let firmware = firmware();
+    let error = firmware
+        .replace_component("ui_nu", &vec![0; 0x1000000], None)
+        .unwrap_err();
+    assert_eq!(error.to_string(), "ui_nu is limited to 16777215 bytes");
+
+    // the sound DB flag sharing the word survives the new length
+    let replaced = firmware
+        .replace_component("ui_nu", &vec![1; 0x10000], None)
+        .unwrap();
+    assert_eq!(replaced[0..4], [0, 0, 1, 1]);
+    let replaced = parse(&replaced);
+    assert_eq!(layout(&replaced)[0], ("ui_nu", 0x10000, 11));
+    assert!(replaced.find_file("sound_dbnu").is_some());
+}
+
+#[test]
+fn sets_trailer_versions() {
+    let firmware = firmware();
+    let version = |data: &[u8], name: &str| {
+        let firmware = parse(data);
+        let file = firmware.find_file(name).unwrap();
+        let model = file.trailer().unwrap().model();
+        (file.info().base().version(), model)
+    };
+
+    // plain trailer word, keeping or replacing the model bits
+    let out = firmware.set_version("dsp_nu", 1023, None).unwrap();
+    assert!(version(&out, "dsp_nu") == (1023, Some(RDModel::R8)));
+    let out = firmware.set_version("ble", 0, Some(RDModel::R7)).unwrap();
+    assert!(version(&out, "ble") == (0, Some(RDModel::R7)));
+    assert_eq!(out.len(), firmware.size as usize);
+
+    // encoded sound DB trailer block, the body being left alone
+    let out = firmware.set_version("sound_dbla2", 300, None).unwrap();
+    assert!(version(&out, "sound_dbla2") == (300, None));
+    let rebuilt = parse(&out);
+    let (old, new) = (
+        trimmed(&firmware, "sound_dbla2"),
+        trimmed(&rebuilt, "sound_dbla2"),
+    );
+    assert_eq!(old[..old.len() - 12], new[..new.len() - 12]);
+
+    // plain 32-bit GPS DB version
+    let out = firmware.set_version("gps_db", 0x7FFFFFFF, None).unwrap();
+    assert!(version(&out, "gps_db") == (0x7FFFFFFF, None));
+
+    for (name, version, model, message) in [
+        (
+            "dsp_nu",
+            0x400,
+            None,
+            "Version 1024 does not fit in 10 bits",
+        ),
+        ("sound_dbnu", -1, None, "Version -1 does not fit in 10 bits"),
+        ("gps_db", -1, None, "Version -1 does not fit in 31 bits"),
+        ("gps_db", 1, Some(RDModel::R8), "gps_db has no model bits"),
+        (
+            "sound_dbnu",
+            1,
+            Some(RDModel::R8),
+            "sound_dbnu has no model bits",
+        ),
+        ("keypad", 1, None, "Component not found: keypad"),
+    ] {
+        let error = firmware.set_version(name, version, model).unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+}
+
+#[test]
+fn sets_format_version() {
+    let firmware = firmware();
+    let out = firmware.set_format_version(7).unwrap();
+    let rebuilt = parse(&out);
+    assert_eq!(rebuilt.metadata.as_ref().unwrap().format_version, Some(7));
+    assert_eq!(layout(&rebuilt), layout(&firmware));
+    assert_eq!(
+        firmware.set_format_version(-1).unwrap_err().to_string(),
+        "Invalid format version: -1"
+    );
+
+    let legacy = parse(
+        &BlobSpec::legacy(RDModel::R3)
+            .with(ComponentSpec::new("ui_nu", 10, 1))
+            .build()
+            .data,
+    );
+    assert_eq!(
+        legacy.set_format_version(1).unwrap_err().to_string(),
+        "BLOB has no NMGF record"
+    );
+}
@@ -0,0 +1,386 @@
+#[cfg(feature = "serde")]
+use std::{fs, path::PathBuf, process::Command};
+
+use ufwt::file::{ExtractMode, FileInfo, UnidenFirmware};
+use ufwt::format::{decode_old_model, GpsDbCountry, RDModel, OLD_NZ_GPS_DB_KEY};
+#[cfg(feature = "serde")]
+use ufwt::gpsdb::read_pois;
+use ufwt::gpsdb::{distance, CameraType, GpsDb, GpsDbDiff, Poi, RECORD_LEN};
+use ufwt::synth::{BlobSpec, ComponentSpec};
+
+#[cfg(feature = "serde")]
+fn write(name: &str, contents: &str) -> PathBuf {
+    write_bytes(name, contents.as_bytes())
+}
+
+#[cfg(feature = "serde")]
+fn write_bytes(name: &str, contents: &[u8]) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn expected() -> Vec<Poi> {
+    vec![
+        Poi {
+            latitude: -36.848461,
+            longitude: 174.763336,
+            camera: CameraType::Speed,
+            speed: 50,
+            heading: Some(270),
+        },
+        Poi {
+            latitude: -41.2865,
+            longitude: 174.776236,
+            camera: CameraType::RedLight,
+            speed: 0,
+            heading: None,
+        },
+        Poi {
+            latitude: -43.5321,
+            longitude: 172.6362,
+            camera: CameraType::Other(9),
+            speed: 100,
+            heading: Some(0),
+        },
+    ]
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn imports_pois() {
+    let csv = write(
+        "pois.csv",
+        "latitude, longitude, type, speed, heading\n\
+         -36.848461, 174.763336, speed, 50, 270\n\
+         -41.2865, 174.776236, red_light, 0,\n\
+         -43.5321, 172.6362, type_9, 100, 0\n",
+    );
+    let gpx = write(
+        "pois.gpx",
+        r#"<?xml version="1.0"?>
+<gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+  <wpt lat="-36.848461" lon="174.763336">
+    <type>speed</type>
+    <extensions><speed>50</speed><course>270</course></extensions>
+  </wpt>
+  <wpt lat="-41.2865" lon="174.776236"><type>red-light</type></wpt>
+  <wpt lat="-43.5321" lon="172.6362">
+    <type>9</type>
+    <extensions><speed>99.6</speed><course>0</course></extensions>
+  </wpt>
+</gpx>"#,
+    );
+    let geojson = write(
+        "pois.geojson",
+        r#"{"type": "FeatureCollection", "features": [
+  {"type": "Feature", "geometry": {"type": "Point", "coordinates": [174.763336, -36.848461]},
+   "properties": {"type": "speed", "speed": 50, "heading": 270}},
+  {"type": "Feature", "geometry": {"type": "Point", "coordinates": [174.776236, -41.2865]},
+   "properties": {"type": "red_light"}},
+  {"type": "Feature", "geometry": {"type": "Point", "coordinates": [172.6362, -43.5321, 12.0]},
+   "properties": {"type": "type_9", "speed": 100, "heading": 0}}
+]}"#,
+    );
+    for path in [csv, gpx, geojson] {
+        assert_eq!(read_pois(&path).unwrap(), expected(), "{}", path.display());
+    }
+
+    for (name, contents, message) in [
+        (
+            "range.csv",
+            "latitude,longitude,type\n91,0,speed\n",
+            "Coordinates out of range: 91, 0",
+        ),
+        (
+            "heading.geojson",
+            r#"{"features": [{"geometry": {"coordinates": [0, 0]},
+                "properties": {"type": "mobile", "heading": 360}}]}"#,
+            "Heading out of range: 360",
+        ),
+        (
+            "camera.gpx",
+            r#"<gpx><wpt lat="0" lon="0"><type>tram</type></wpt></gpx>"#,
+            "Unknown camera type: tram",
+        ),
+        (
+            "speed.csv",
+            "latitude,longitude,type,speed\n0,0,speed,300\n",
+            "CSV deserialize error",
+        ),
+        (
+            "speed.gpx",
+            r#"<gpx><wpt lat="0" lon="0"><speed>255.5</speed></wpt></gpx>"#,
+            "Speed out of range: 256",
+        ),
+        (
+            "course.gpx",
+            r#"<gpx><wpt lat="0" lon="0"><course>-90</course></wpt></gpx>"#,
+            "Heading out of range: -90",
+        ),
+        (
+            "speed.geojson",
+            r#"{"features": [{"geometry": {"coordinates": [0, 0]},
+                "properties": {"type": "speed", "speed": 300}}]}"#,
+            "invalid value: integer `300`",
+        ),
+        ("pois.kml", "", "Unknown POI file format: "),
+    ] {
+        let error = read_pois(&write(name, contents)).unwrap_err().to_string();
+        assert!(error.starts_with(message), "{}: {}", name, error);
+    }
+}
+
+#[test]
+fn builds_and_inserts_gps_db() {
+    let gps_db = GpsDb {
+        country: GpsDbCountry::Nz,
+        version: 20240101,
+        pois: expected(),
+    };
+    let section = gps_db.build_section().unwrap();
+    assert!(section.ends_with(b"DFDBDRSWGDB"));
+
+    let mut firmware = UnidenFirmware::from_bytes(
+        &BlobSpec::merged(RDModel::R8, 3)
+            .with(ComponentSpec::new("ui_nu", 1000, 11))
+            .with(ComponentSpec::new("ble", 100, 21))
+            .build()
+            .data,
+    );
+    firmware.read_buffer().unwrap();
+    let out = firmware.insert_section("GPSD", "gps_db", &section).unwrap();
+    let mut rebuilt = UnidenFirmware::from_bytes(&out);
+    rebuilt.read_buffer().unwrap();
+
+    let file = rebuilt.find_file("gps_db").unwrap();
+    let FileInfo::GpsDb(info) = file.info() else {
+        panic!("gps_db without GPS DB info");
+    };
+    assert!(info.country() == Some(GpsDbCountry::Nz));
+    assert_eq!(info.poi(), 3);
+    assert_eq!(info.info().version(), 20240101);
+
+    // the records are encoded with the NZ key, coordinates in millionths of a degree
+    let data = rebuilt.file_slice(file, ExtractMode::Trimmed);
+    assert_eq!(data.len(), 3 * RECORD_LEN + 12);
+    let records = decode_old_model(OLD_NZ_GPS_DB_KEY, data, 0, 3 * RECORD_LEN);
+    assert_eq!(records[0..4], (-36848461i32).to_le_bytes());
+    assert_eq!(records[4..8], 174763336i32.to_le_bytes());
+    assert_eq!(records[8..12], [1, 50, 0x0E, 0x01]);
+    assert_eq!(records[16 + 10..16 + 12], [0xFF, 0xFF]);
+
+    let parsed = GpsDb::parse(data).unwrap();
+    assert!(parsed.country == GpsDbCountry::Nz);
+    assert_eq!(parsed.version, 20240101);
+    assert_eq!(parsed.pois, expected());
+    assert!(GpsDb::parse(&section).is_ok());
+
+    // trailing bytes the assumed layout can't account for are refused
+    let mut extra = vec![0; 4];
+    extra.extend_from_slice(data);
+    assert_eq!(
+        GpsDb::parse(&extra).err().unwrap().to_string(),
+        "3 POIs do not make up 52 bytes, the GPS DB does not follow the assumed POI record layout"
+    );
+    let error = GpsDb {
+        country: GpsDbCountry::Eu,
+        ..gps_db
+    }
+    .build()
+    .unwrap_err();
+    assert_eq!(error.to_string(), "There is no old encoding GPS DB for EU");
+}
+
+/// A standalone GPS DB package holding `pois`, its `GASD` section first if `gasd_first`.
+fn package(pois: Vec<Poi>, gasd_first: bool) -> Vec<u8> {
+    let gpsd = ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Nz, 4);
+    let gasd = ComponentSpec::new("gps_db_second", 44, 124).gps_db(GpsDbCountry::Eu, 2);
+    let spec = if gasd_first {
+        BlobSpec::package().with(gasd).with(gpsd)
+    } else {
+        BlobSpec::package().with(gpsd).with(gasd)
+    };
+    let mut firmware = UnidenFirmware::from_bytes(&spec.build().data);
+    firmware.read_buffer().unwrap();
+    let gps_db = GpsDb {
+        country: GpsDbCountry::Nz,
+        version: 20240101,
+        pois,
+    };
+    firmware
+        .replace_component("gps_db", &gps_db.build_section().unwrap(), None)
+        .unwrap()
+}
+
+#[test]
+fn reads_gps_db_packages() {
+    for gasd_first in [false, true] {
+        let data = package(expected(), gasd_first);
+        let mut firmware = UnidenFirmware::from_bytes(&data);
+        firmware.read_buffer().unwrap();
+        let names: Vec<_> = firmware
+            .files()
+            .iter()
+            .map(|file| file.kind().to_name())
+            .collect();
+        if gasd_first {
+            assert_eq!(names, ["gps_db_second", "gps_db"]);
+        } else {
+            assert_eq!(names, ["gps_db", "gps_db_second"]);
+        }
+
+        let gps_db = GpsDb::parse_file(&data).unwrap();
+        assert_eq!(gps_db.version, 20240101);
+        assert_eq!(gps_db.pois, expected());
+    }
+
+    let gasd_only = BlobSpec::package()
+        .with(ComponentSpec::new("gps_db_second", 44, 124).gps_db(GpsDbCountry::Eu, 2))
+        .build()
+        .data;
+    assert_eq!(
+        GpsDb::parse_file(&gasd_only).err().unwrap().to_string(),
+        "GPS DB package without a GPSD section"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn diffs_gps_db_packages() {
+    let mut pois = expected();
+    pois.pop();
+    let old = write_bytes("old_package.bin", &package(expected(), true));
+    let new = write_bytes("new_package.bin", &package(pois, false));
+    let gasd_only = write_bytes(
+        "gasd_package.bin",
+        &BlobSpec::package()
+            .with(ComponentSpec::new("gps_db_second", 44, 124).gps_db(GpsDbCountry::Eu, 2))
+            .build()
+            .data,
+    );
+
+    let diff = |old: &PathBuf, new: &PathBuf| {
+        Command::new(env!("CARGO_BIN_EXE_ufwt"))
+            .arg("gpsdb-diff")
+            .args([old, new])
+            .output()
+            .unwrap()
+    };
+    let output = diff(&old, &new);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("Added: 0, removed: 1, modified: 0, unchanged: 2"),
+        "{}",
+        stdout
+    );
+
+    // a package without a GPS DB is reported rather than panicking
+    let output = diff(&old, &gasd_only);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap().trim(),
+        format!(
+            "Couldn't decode {}: GPS DB package without a GPSD section",
+            gasd_only.display()
+        )
+    );
+}
+
+fn poi(latitude: f64, longitude: f64, camera: CameraType, speed: u8) -> Poi {
+    Poi {
+        latitude,
+        longitude,
+        camera,
+        speed,
+        heading: None,
+    }
+}
+
+#[test]
+fn diffs_gps_dbs() {
+    // POIs are bucketed in cells as wide as the tolerance, in degrees
+    let tolerance = 25.0;
+    let cell = tolerance / (6_371_000.0 * std::f64::consts::PI / 180.0);
+    let edge = 1000.0 * cell;
+    let old = vec![
+        poi(10.0, 20.0, CameraType::Speed, 50),
+        // moved 20 m north over a cell boundary
+        poi(edge - 0.4 * cell, 20.0, CameraType::Speed, 60),
+        // moved 20 m east into the next cell but one, a degree of longitude being half
+        // as long at 60°
+        poi(60.0, edge - 0.3 * cell, CameraType::RedLight, 0),
+        // moved 5 m, its speed limit changed
+        poi(-33.0, 151.0, CameraType::Speed, 60),
+        // moved 30 m, too far to be the same camera
+        poi(edge - 0.1 * cell, -70.0, CameraType::Mobile, 0),
+        poi(45.0, 9.0, CameraType::AverageSpeed, 130),
+    ];
+    let new = vec![
+        poi(-33.0 + 0.2 * cell, 151.0, CameraType::Speed, 50),
+        poi(60.0, edge + 1.3 * cell, CameraType::RedLight, 0),
+        poi(10.0, 20.0, CameraType::Speed, 50),
+        poi(edge + 0.4 * cell, 20.0, CameraType::Speed, 60),
+        poi(edge + 1.1 * cell, -70.0, CameraType::Mobile, 0),
+        poi(51.5, -0.1, CameraType::RedLightSpeed, 30),
+    ];
+    assert!((distance(&old[1], &new[3]) - 20.0).abs() < 0.1);
+    assert!((distance(&old[2], &new[1]) - 20.0).abs() < 0.1);
+    assert!((distance(&old[4], &new[4]) - 30.0).abs() < 0.1);
+
+    let diff = GpsDbDiff::new(&old, &new, tolerance);
+    assert_eq!(diff.unchanged, 3);
+    assert_eq!(diff.modified, [(old[3].clone(), new[0].clone())]);
+    assert_eq!(diff.removed, [old[4].clone(), old[5].clone()]);
+    assert_eq!(diff.added, [new[4].clone(), new[5].clone()]);
+
+    // with a larger tolerance the 30 m move is the same camera
+    let diff = GpsDbDiff::new(&old, &new, 35.0);
+    assert_eq!(diff.unchanged, 4);
+    assert_eq!(diff.removed, [old[5].clone()]);
+
+    // cameras 20 m apart on either side of the antimeridian are the same
+    let pair = [
+        poi(0.0, 180.0 - 0.4 * cell, CameraType::Speed, 80),
+        poi(0.0, -180.0 + 0.4 * cell, CameraType::Speed, 70),
+    ];
+    assert!((distance(&pair[0], &pair[1]) - 20.0).abs() < 0.2);
+    let diff = GpsDbDiff::new(&pair[..1], &pair[1..], tolerance);
+    assert_eq!(diff.modified, [(pair[0].clone(), pair[1].clone())]);
+    let diff = GpsDbDiff::new(&pair[1..], &pair[..1], tolerance);
+    assert_eq!(diff.modified, [(pair[1].clone(), pair[0].clone())]);
+
+    // each POI matches once, closest pairs first
+    let twins = vec![poi(0.0, 0.0, CameraType::Speed, 50); 2];
+    let diff = GpsDbDiff::new(&twins[..1], &twins, tolerance);
+    assert_eq!((diff.unchanged, diff.added.len()), (1, 1));
+
+    let diff = GpsDbDiff::new(&old, &new, tolerance);
+    let totals = diff.totals();
+    assert_eq!(totals[&CameraType::Mobile].added, 1);
+    assert_eq!(totals[&CameraType::Mobile].removed, 1);
+    assert_eq!(totals[&CameraType::Speed].modified, 1);
+    assert_eq!(totals[&CameraType::RedLightSpeed].added, 1);
+
+    #[cfg(feature = "serde")]
+    {
+        let geojson = diff.to_geojson();
+        let features = geojson["features"].as_array().unwrap();
+        let changes: Vec<_> = features
+            .iter()
+            .map(|feature| feature["properties"]["change"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            changes,
+            ["added", "added", "removed", "removed", "modified"]
+        );
+        assert_eq!(
+            features[1]["geometry"]["coordinates"],
+            serde_json::json!([-0.1, 51.5])
+        );
+        assert_eq!(features[4]["properties"]["speed"], 50);
+        assert_eq!(features[4]["properties"]["previous"]["speed"], 60);
+        assert_eq!(features[4]["properties"]["type"], "speed");
+    }
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "serde")]
+
+use std::{fs, path::PathBuf, process::Command};
+
+use ufwt::file::{ExtractMode, UnidenFirmware};
+use ufwt::format::{GpsDbCountry, RDModel};
+use ufwt::hash::{ComponentHashes, KnownHashes};
+use ufwt::synth::{BlobSpec, ComponentSpec};
+
+fn write_bytes(name: &str, contents: &[u8]) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn parse(data: &[u8]) -> UnidenFirmware {
+    let mut firmware = UnidenFirmware::from_bytes(data);
+    firmware.read_buffer().unwrap();
+    firmware
+}
+
+fn spec() -> BlobSpec {
+    BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("dsp_nu", 700, 12))
+        .with(ComponentSpec::new("sound_dbnu", 112, 7))
+        .with(ComponentSpec::new("ble", 100, 21))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Us, 4))
+}
+
+#[test]
+fn identifies_known_releases_by_hash() {
+    let hashes = ComponentHashes::of(b"123456789");
+    assert_eq!(hashes.crc32_hex(), "cbf43926");
+    assert_eq!(
+        ComponentHashes::of(b"abc").sha256,
+        *b"\xba\x78\x16\xbf\x8f\x01\xcf\xea\x41\x41\x40\xde\x5d\xae\x22\x23\
+           \xb0\x03\x61\xa3\x96\x17\x7a\x9c\xb4\x10\xff\x61\xf2\x00\x15\xad"
+    );
+
+    let firmware = parse(&spec().build().data);
+    let hashes = ComponentHashes::of_components(&firmware);
+    assert_eq!(hashes.len(), firmware.files().len());
+    let (name, dsp) = hashes[1];
+    assert_eq!(name, "dsp_nu");
+    let file = firmware.find_file("dsp_nu").unwrap();
+    assert_eq!(
+        dsp,
+        ComponentHashes::of(firmware.file_slice(file, ExtractMode::Trimmed))
+    );
+
+    let mut known = KnownHashes::bundled();
+    let bundled = known.releases().len();
+    known
+        .add_json(&format!(
+            r#"[{{"model": "R8", "component": "dsp_nu", "release": "1.0", "sha1": "{}"}},
+                {{"model": "R7", "component": "STDS", "release": "2.0", "crc32": "{}"}}]"#,
+            ufwt::util::to_hex(&dsp.sha1),
+            dsp.crc32_hex()
+        ))
+        .unwrap_or_else(|e| panic!("{}", e));
+    let releases: Vec<_> = known
+        .lookup(&dsp, Some("dsp_nu"))
+        .map(|release| release.release.as_str())
+        .collect();
+    assert_eq!(releases, ["1.0"]);
+    let stm = known.lookup(&dsp, None).nth(1).unwrap();
+    assert_eq!(stm.component, "dsp_stm");
+    assert!(stm.model == RDModel::R7);
+    assert_eq!(known.lookup(&hashes[0].1, None).count(), 0);
+
+    let error = known
+        .add_json(r#"[{"model": "R8", "component": "ui_nu", "release": "1.0"}]"#)
+        .unwrap_err();
+    assert_eq!(error.to_string(), "No hash given for R8 ui_nu release 1.0");
+    assert_eq!(known.releases().len(), bundled + 2);
+}
+
+#[test]
+fn reports_known_releases_from_hash_dbs() {
+    let data = spec().build().data;
+    let blob = write_bytes("known.bin", &data);
+    let firmware = parse(&data);
+    let slice = |name: &str, mode: ExtractMode| {
+        firmware.file_slice(firmware.find_file(name).unwrap(), mode)
+    };
+    let dsp = ComponentHashes::of(slice("dsp_nu", ExtractMode::Trimmed));
+    let sound = ComponentHashes::of(slice("sound_dbnu", ExtractMode::Trimmed));
+    let db = write_bytes(
+        "known.json",
+        format!(
+            r#"[{{"model": "R8", "component": "dsp_nu", "release": "1.0", "crc32": "{}"}},
+                {{"model": "R8", "component": "sound_dbnu", "release": "1.1", "crc32": "{}"}}]"#,
+            dsp.crc32_hex(),
+            sound.crc32_hex()
+        )
+        .as_bytes(),
+    );
+
+    let run = |args: &[&str], files: &[&PathBuf]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ufwt"))
+            .args(args)
+            .args(files)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", args);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let db = db.to_str().unwrap();
+    // every component is hashed, and looked up in the bundled and given DBs
+    let plain = run(&["parse"], &[&blob]);
+    assert!(plain.contains("Hashes:\n   - ui_nu: crc32 "));
+    assert!(plain.contains(&format!("dsp_nu: crc32 {}", dsp.crc32_hex())));
+    assert!(!plain.contains("Known releases:"));
+    let known = run(&["parse", "--hash-db", db], &[&blob]);
+    assert!(known.contains(
+        "Known releases:\n   - dsp_nu: Uniden R8 release 1.0\n   - sound_dbnu: Uniden R8 release 1.1"
+    ));
+
+    // extracted images are looked up by the part they were trimmed to
+    let padded = write_bytes("known-dsp.bin", slice("dsp_nu", ExtractMode::Padded));
+    let raw_dsp = write_bytes("known-dsp-raw.bin", slice("dsp_nu", ExtractMode::Raw));
+    let raw_sound = write_bytes("known-sound.bin", slice("sound_dbnu", ExtractMode::Raw));
+    let identified = run(
+        &["identify", "--hash-db", db],
+        &[&blob, &padded, &raw_dsp, &raw_sound],
+    );
+    assert_eq!(
+        identified
+            .matches("Known release: dsp_nu of Uniden R8 release 1.0")
+            .count(),
+        2
+    );
+    assert!(identified.contains("Known release: sound_dbnu of Uniden R8 release 1.1"));
+    let alone = run(&["identify", "--hash-db", db], &[&raw_sound]);
+    assert!(alone.contains("Known release: sound_dbnu of Uniden R8 release 1.1"));
+}
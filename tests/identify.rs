@@ -0,0 +1,224 @@
+use ufwt::file::{ExtractMode, UnidenFirmware};
+use ufwt::format::{GpsDbCountry, RDModel};
+use ufwt::gpsdb::{CameraType, GpsDb, Poi};
+use ufwt::identify::{identify, identify_extracted, FileType, Identification};
+use ufwt::sound::{SampleFormat, SoundDb};
+use ufwt::synth::{BlobSpec, ComponentSpec};
+
+fn blob() -> Vec<u8> {
+    BlobSpec::merged(RDModel::R7, 2)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("dsp_nu", 700, 12))
+        .with(ComponentSpec::new("sound_dbnu", 112, 7))
+        .with(ComponentSpec::new("ble", 100, 21))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Us, 4))
+        .build()
+        .data
+}
+
+fn kind(identification: &Identification) -> Option<&'static str> {
+    identification.kind.as_ref().map(|kind| kind.to_name())
+}
+
+#[test]
+fn identifies_blobs() {
+    let merged = identify(&blob());
+    assert!(merged.file_type == FileType::MergedBlob);
+    assert!(merged.model == Some(RDModel::R7));
+    assert_eq!(merged.format_version, Some(2));
+    assert_eq!(
+        merged.components,
+        ["ui_nu", "dsp_nu", "sound_dbnu", "ble", "gps_db"]
+    );
+    assert_eq!(
+        merged.commands(),
+        [
+            "parse",
+            "extract",
+            "replace",
+            "set-version",
+            "sounds",
+            "import-pois --insert"
+        ]
+    );
+
+    let legacy = identify(
+        &BlobSpec::legacy(RDModel::R3)
+            .with(ComponentSpec::new("ui_nu", 300, 1))
+            .with(ComponentSpec::new("gps_nu", 200, 2))
+            .build()
+            .data,
+    );
+    assert!(legacy.file_type == FileType::LegacyBlob);
+    assert!(legacy.model == Some(RDModel::R3));
+    assert_eq!(legacy.format_version, None);
+    assert_eq!(legacy.components, ["ui_nu", "gps_nu"]);
+    assert!(!legacy.commands().contains(&"sounds"));
+
+    let package = identify(
+        &BlobSpec::package()
+            .with(ComponentSpec::new("gps_db", 76, 9).gps_db(GpsDbCountry::Nz, 4))
+            .build()
+            .data,
+    );
+    assert!(package.file_type == FileType::GpsDbPackage);
+    assert!(package.country == Some(GpsDbCountry::Nz));
+    assert_eq!(package.components, ["gps_db"]);
+    assert!(package.commands().contains(&"gpsdb-diff"));
+}
+
+#[test]
+fn identifies_components_and_databases() {
+    let data = blob();
+    let mut firmware = UnidenFirmware::from_bytes(&data);
+    firmware.read_buffer().unwrap();
+    let extract = |name: &str, mode: ExtractMode| {
+        firmware
+            .file_slice(firmware.find_file(name).unwrap(), mode)
+            .to_vec()
+    };
+
+    // sections extracted with their trailer and terminator
+    let dsp = identify(&extract("dsp_nu", ExtractMode::Raw));
+    assert!(dsp.file_type == FileType::Component);
+    assert_eq!(kind(&dsp), Some("dsp_nu"));
+    assert_eq!(dsp.version, Some(12));
+    assert!(dsp.model == Some(RDModel::R7));
+    // `replace` can't tell the padding of a raw image from its payload
+    assert!(dsp.commands().is_empty());
+
+    let gps = identify(&extract("gps_db", ExtractMode::Raw));
+    assert!(gps.file_type == FileType::Component);
+    assert_eq!(kind(&gps), Some("gps_db"));
+    assert_eq!(gps.version, Some(123));
+    assert!(gps.country == Some(GpsDbCountry::Us));
+    assert_eq!(gps.commands(), ["replace --with", "gpsdb-diff"]);
+
+    // GPS DBs without their terminator, as written by `extract --trim`
+    for (data, country, version) in [
+        (
+            extract("gps_db", ExtractMode::Trimmed),
+            GpsDbCountry::Us,
+            123,
+        ),
+        (
+            GpsDb {
+                country: GpsDbCountry::Il,
+                version: 77,
+                pois: vec![Poi {
+                    latitude: 32.08,
+                    longitude: 34.78,
+                    camera: CameraType::Speed,
+                    speed: 90,
+                    heading: None,
+                }],
+            }
+            .build()
+            .unwrap(),
+            GpsDbCountry::Il,
+            77,
+        ),
+    ] {
+        let gps_db = identify(&data);
+        assert!(gps_db.file_type == FileType::GpsDb);
+        assert_eq!(kind(&gps_db), Some("gps_db"));
+        assert!(gps_db.country == Some(country));
+        assert_eq!(gps_db.version, Some(version));
+        assert!(gps_db.model.is_none());
+    }
+
+    let sound_db = SoundDb {
+        version: 300,
+        format: SampleFormat::Pcm8,
+        sample_rate: 8000,
+        prompts: vec![vec![0x80; 9]],
+    };
+    let sound = identify(&sound_db.build());
+    assert!(sound.file_type == FileType::SoundDb);
+    assert_eq!(sound.version, Some(300));
+    assert_eq!(sound.commands(), ["replace --with"]);
+}
+
+#[test]
+fn identifies_extracted_components() {
+    let data = blob();
+    let mut firmware = UnidenFirmware::from_bytes(&data);
+    firmware.read_buffer().unwrap();
+    let extract = |name: &str, mode: ExtractMode| {
+        firmware
+            .file_slice(firmware.find_file(name).unwrap(), mode)
+            .to_vec()
+    };
+
+    for (name, mode, commands) in [
+        ("dsp_nu", ExtractMode::Trimmed, &["replace --with"][..]),
+        ("dsp_nu", ExtractMode::Padded, &[]),
+        ("dsp_nu", ExtractMode::Raw, &[]),
+        ("ble", ExtractMode::Padded, &[]),
+        // DBs aren't padded, their declared length being preferred
+        ("sound_dbnu", ExtractMode::Padded, &["replace --with"]),
+        (
+            "gps_db",
+            ExtractMode::Raw,
+            &["replace --with", "gpsdb-diff"],
+        ),
+    ] {
+        let extracted = identify_extracted(&extract(name, mode), &firmware).unwrap();
+        assert!(extracted.file_type == FileType::Extracted);
+        assert_eq!(kind(&extracted), Some(name));
+        let expected = match (name, mode) {
+            ("sound_dbnu", ExtractMode::Padded) => ExtractMode::Trimmed,
+            _ => mode,
+        };
+        assert!(
+            extracted.mode == Some(expected),
+            "{} {}",
+            name,
+            mode.to_name()
+        );
+        assert!(extracted.model == Some(RDModel::R7));
+        assert_eq!(
+            extracted.commands(),
+            commands,
+            "{} {}",
+            name,
+            mode.to_name()
+        );
+    }
+    let dsp = identify_extracted(&extract("dsp_nu", ExtractMode::Padded), &firmware).unwrap();
+    assert_eq!(dsp.version, Some(12));
+    let gps = identify_extracted(&extract("gps_db", ExtractMode::Trimmed), &firmware).unwrap();
+    assert!(gps.country == Some(GpsDbCountry::Us));
+
+    // the default `extract` output can't be told apart without its BLOB
+    assert!(identify(&extract("dsp_nu", ExtractMode::Padded)).file_type == FileType::Unknown);
+    let mut edited = extract("dsp_nu", ExtractMode::Trimmed);
+    edited[0] ^= 1;
+    assert!(identify_extracted(&edited, &firmware).is_none());
+    assert!(identify_extracted(&[], &firmware).is_none());
+}
+
+#[test]
+fn identifies_unknown_files() {
+    let data = blob();
+    let mut junk_tail = data.clone();
+    junk_tail.extend_from_slice(b"JUNK");
+    let noise: Vec<u8> = (0..4096u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+        .collect();
+
+    for data in [
+        &[][..],
+        &b"DRSWDSP"[..],
+        &noise,
+        // the header lengths don't lead to the terminators
+        &data[..600],
+        // nor to a section tag or the end of the file
+        &junk_tail,
+    ] {
+        let identification = identify(data);
+        assert!(identification.file_type == FileType::Unknown);
+        assert!(identification.kind.is_none());
+        assert!(identification.commands().is_empty());
+    }
+}
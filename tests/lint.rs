@@ -0,0 +1,135 @@
+#[cfg(feature = "serde")]
+use std::{fs, path::PathBuf, process::Command};
+
+use ufwt::file::UnidenFirmware;
+use ufwt::format::{GpsDbCountry, RDModel};
+use ufwt::lint::{lint, Severity};
+use ufwt::synth::{BlobSpec, ComponentSpec};
+
+fn spec(dsp_nu: ComponentSpec) -> BlobSpec {
+    BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(dsp_nu)
+        .with(ComponentSpec::new("ble", 100, 21))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Us, 4))
+}
+
+fn legacy() -> Vec<u8> {
+    BlobSpec::legacy(RDModel::R3)
+        .with(ComponentSpec::new("ui_nu", 300, 1))
+        .with(ComponentSpec::new("dsp_nu", 200, 2))
+        .build()
+        .data
+}
+
+/// Code, severity, offset and component of every finding.
+fn findings(data: &[u8]) -> Vec<(&'static str, Severity, Option<u64>, Option<&'static str>)> {
+    let mut firmware = UnidenFirmware::from_bytes(data);
+    firmware.lenient = true;
+    firmware.read_buffer().unwrap();
+    lint(&firmware)
+        .iter()
+        .map(|finding| {
+            (
+                finding.code,
+                finding.severity,
+                finding.offset,
+                finding.component,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn accepts_clean_blobs() {
+    assert!(findings(&spec(ComponentSpec::new("dsp_nu", 700, 12)).build().data).is_empty());
+    assert!(findings(&legacy()).is_empty());
+}
+
+#[test]
+fn reports_padding_and_models() {
+    let blob = spec(ComponentSpec::new("dsp_nu", 700, 12).fill(0x5A)).build();
+    let dsp = blob.component("dsp_nu").unwrap();
+    assert_eq!(
+        findings(&blob.data),
+        [(
+            "nonstandard-padding",
+            Severity::Warning,
+            Some((dsp.offset + 700) as u64),
+            Some("dsp_nu")
+        )]
+    );
+
+    let blob = spec(ComponentSpec::new("dsp_nu", 700, 12).model(RDModel::R7)).build();
+    assert_eq!(
+        findings(&blob.data),
+        [("model-mismatch", Severity::Warning, None, Some("dsp_nu"))]
+    );
+}
+
+#[test]
+fn reports_misplaced_records_and_gaps() {
+    // NMGF moved in front of the GPS DB section
+    let blob = spec(ComponentSpec::new("dsp_nu", 700, 12)).build();
+    let (body, nmgf) = blob.data.split_at(blob.data.len() - 12);
+    let tag = blob.component("gps_db").unwrap().tag_offset.unwrap();
+    let mut data = body.to_vec();
+    data.splice(tag..tag, nmgf.iter().copied());
+    assert_eq!(
+        findings(&data),
+        [("nmgf-not-at-end", Severity::Warning, Some(tag as u64), None)]
+    );
+
+    let mut data = legacy();
+    let end = data.len() as u64;
+    data.extend_from_slice(&[1, 2, 3]);
+    assert_eq!(
+        findings(&data),
+        [("unread-gap", Severity::Warning, Some(end), None)]
+    );
+
+    data.extend_from_slice(b"JUNK");
+    assert_eq!(
+        findings(&data),
+        [
+            ("unknown-tag", Severity::Warning, Some(end), None),
+            ("unread-gap", Severity::Warning, Some(end), None)
+        ]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn fails_on_severity_threshold() {
+    let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let path = directory.join("lint_padding.bin");
+    fs::write(
+        &path,
+        spec(ComponentSpec::new("dsp_nu", 700, 12).fill(0x5A))
+            .build()
+            .data,
+    )
+    .unwrap();
+
+    let lint = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_ufwt"))
+            .arg("lint")
+            .arg(&path)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    // a warning only fails the default error threshold when asked to
+    for (args, code) in [
+        (&[][..], 0),
+        (&["--fail-on", "error"][..], 0),
+        (&["--fail-on", "warning"][..], 1),
+        (&["--fail-on", "info", "--json"][..], 1),
+    ] {
+        let output = lint(args);
+        assert_eq!(output.status.code(), Some(code), "{:?}", args);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("nonstandard-padding"), "{}", stdout);
+    }
+    assert!(!lint(&["--fail-on", "fatal"]).status.success());
+}
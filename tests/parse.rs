@@ -0,0 +1,288 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ufwt::file::{handle_gpsdb_file_info, ExtractMode, FormatGeneration, UnidenFirmware};
+use ufwt::format::{encode_old_model, terminator_for_component, GpsDbCountry, RDModel};
+use ufwt::synth::{payload, BlobSpec, ComponentSpec, SynthBlob};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ufwt-test-{}-{}", std::process::id(), name))
+}
+
+fn parse(data: &[u8]) -> UnidenFirmware {
+    let mut firmware = UnidenFirmware::from_bytes(data);
+    firmware.read_buffer().unwrap();
+    firmware
+}
+
+fn full_spec() -> BlobSpec {
+    BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("dsp_nu", 700, 12))
+        .with(ComponentSpec::new("gps_nu", 300, 13))
+        .with(ComponentSpec::new("sound_dbnu", 112, 7))
+        .with(ComponentSpec::new("ui_stm", 600, 20))
+        .with(ComponentSpec::new("ble", 100, 21))
+        .with(ComponentSpec::new("keypad", 512, 22).fill(0x00))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Us, 4))
+        .with(ComponentSpec::new("gps_db_second", 44, 124).gps_db(GpsDbCountry::Eu, 2))
+        .with(ComponentSpec::new("sound_dbla1", 40, 4))
+        .with(ComponentSpec::new("sound_dbla2", 52, 5))
+}
+
+/// Compare every parsed component against where the generator put it.
+fn check_components(firmware: &UnidenFirmware, spec: &BlobSpec, blob: &SynthBlob) {
+    let names: Vec<_> = firmware
+        .files()
+        .iter()
+        .map(|f| f.kind().to_name())
+        .collect();
+    let expected: Vec<_> = blob.components.iter().map(|c| c.name).collect();
+    assert_eq!(names, expected);
+
+    for (file, synth) in firmware.files().iter().zip(&blob.components) {
+        let component = spec
+            .components
+            .iter()
+            .find(|c| c.name == synth.name)
+            .unwrap();
+        let info = file.info().base();
+        assert!(!file.damaged(), "{} is damaged", synth.name);
+        assert_eq!(
+            info.offset() as usize,
+            synth.offset,
+            "{} offset",
+            synth.name
+        );
+        assert_eq!(
+            info.length() as usize,
+            synth.length,
+            "{} length",
+            synth.name
+        );
+        assert_eq!(
+            info.declared_length() as usize,
+            synth.declared_length,
+            "{} declared length",
+            synth.name
+        );
+        assert_eq!(info.version(), component.version, "{} version", synth.name);
+
+        let trailer = file.trailer().unwrap();
+        assert_eq!(trailer.offset() as usize, synth.trailer_offset);
+        assert_eq!(trailer.raw(), &blob.data[synth.trailer_offset..synth.end]);
+        assert_eq!(trailer.version(), component.version);
+        assert_eq!(
+            Some(trailer.terminator()),
+            terminator_for_component(synth.name)
+        );
+
+        match handle_gpsdb_file_info(file.kind()) {
+            Some(gps_db) => {
+                assert_eq!(gps_db.poi(), component.poi);
+                assert!(gps_db.country() == Some(component.country));
+                assert!(trailer.model().is_none());
+            }
+            None if trailer.model().is_some() => {
+                let model = component.model.unwrap_or(spec.model);
+                assert!(trailer.model() == Some(model), "{} model", synth.name);
+                assert!(trailer.extra().is_empty());
+            }
+            None => {}
+        }
+        let skip = if matches!(synth.name, "gps_db_second" | "sound_dbla2") {
+            2
+        } else {
+            0
+        };
+        assert_eq!(trailer.extra().len(), skip, "{} extra bytes", synth.name);
+    }
+}
+
+#[test]
+fn parses_merged_blob() {
+    let spec = full_spec();
+    let blob = spec.build();
+    let firmware = parse(&blob.data);
+
+    let metadata = firmware.metadata.as_ref().unwrap();
+    assert!(metadata.model == RDModel::R8);
+    assert_eq!(metadata.generation, FormatGeneration::Merged);
+    assert_eq!(metadata.format_version, Some(3));
+    assert!(firmware.diagnostics().is_empty());
+    check_components(&firmware, &spec, &blob);
+}
+
+#[test]
+fn parses_legacy_blob_without_sound_db() {
+    let spec = BlobSpec::legacy(RDModel::R3)
+        .with(ComponentSpec::new("ui_nu", 2048, 1))
+        .with(ComponentSpec::new("dsp_nu", 10, 2))
+        .with(ComponentSpec::new("gps_nu", 513, 3));
+    let blob = spec.build();
+    let firmware = parse(&blob.data);
+
+    let metadata = firmware.metadata.as_ref().unwrap();
+    assert!(metadata.model == RDModel::R3);
+    assert_eq!(metadata.generation, FormatGeneration::Legacy);
+    assert_eq!(metadata.format_version, None);
+    // the header is 12 bytes long without the sound DB fields
+    assert_eq!(blob.components[0].offset, 12);
+    check_components(&firmware, &spec, &blob);
+}
+
+#[test]
+fn gps_nu_uses_its_own_length() {
+    // regression: gps_nu used to be stored with the length of dsp_nu
+    let spec = BlobSpec::legacy(RDModel::R7)
+        .with(ComponentSpec::new("ui_nu", 100, 1))
+        .with(ComponentSpec::new("dsp_nu", 3000, 2))
+        .with(ComponentSpec::new("gps_nu", 600, 3));
+    let blob = spec.build();
+    let firmware = parse(&blob.data);
+
+    let gps_nu = firmware.find_file("gps_nu").unwrap();
+    assert_eq!(gps_nu.info().base().length(), 1024);
+    assert_eq!(gps_nu.info().base().declared_length(), 600);
+    assert_eq!(
+        firmware.file_data(gps_nu),
+        &blob.data[blob.component("gps_nu").unwrap().offset..][..1024]
+    );
+}
+
+#[test]
+fn parses_gps_db_package() {
+    let spec = BlobSpec::package()
+        .with(ComponentSpec::new("gps_db", 12 + 16 * 3, 200).gps_db(GpsDbCountry::Nz, 3))
+        .with(ComponentSpec::new("gps_db_second", 12 + 16, 201).gps_db(GpsDbCountry::Nz, 1));
+    let blob = spec.build();
+    let firmware = parse(&blob.data);
+
+    let metadata = firmware.metadata.as_ref().unwrap();
+    assert_eq!(metadata.generation, FormatGeneration::GpsDbPackage);
+    assert!(metadata.model == RDModel::DbNz);
+    check_components(&firmware, &spec, &blob);
+}
+
+#[test]
+fn trailer_model_can_differ_from_blob() {
+    let spec = BlobSpec::merged(RDModel::R8, 1)
+        .with(ComponentSpec::new("ui_nu", 10, 1))
+        .with(ComponentSpec::new("laser_if", 10, 2).model(RDModel::R4));
+    let blob = spec.build();
+    let firmware = parse(&blob.data);
+
+    assert!(firmware.metadata.as_ref().unwrap().model == RDModel::R8);
+    check_components(&firmware, &spec, &blob);
+}
+
+#[test]
+fn extracts_every_mode() {
+    let spec = full_spec();
+    let blob = spec.build();
+    let mut firmware = parse(&blob.data);
+
+    for mode in [ExtractMode::Padded, ExtractMode::Trimmed, ExtractMode::Raw] {
+        let dir = temp_path(&format!("extract-{}", mode.to_name()));
+        fs::create_dir_all(&dir).unwrap();
+        firmware.extract_to(&dir, mode);
+
+        for (synth, component) in blob.components.iter().zip(&spec.components) {
+            let written = fs::read(dir.join(format!("{}.bin", synth.name))).unwrap();
+            let expected = match mode {
+                ExtractMode::Padded => &blob.data[synth.offset..synth.offset + synth.length],
+                ExtractMode::Trimmed => {
+                    &blob.data[synth.offset..synth.offset + synth.declared_length]
+                }
+                ExtractMode::Raw => &blob.data[synth.offset..synth.end],
+            };
+            assert_eq!(
+                written,
+                expected,
+                "{} in {} mode",
+                synth.name,
+                mode.to_name()
+            );
+            if mode == ExtractMode::Trimmed && synth.trailer_offset > synth.offset + 12 {
+                assert!(written.starts_with(&payload(component.name, 12)));
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            let manifest: serde_json::Value =
+                serde_json::from_slice(&fs::read(dir.join("manifest.json")).unwrap()).unwrap();
+            assert_eq!(manifest["mode"], mode.to_name());
+            assert_eq!(manifest["format_version"], 3);
+            let components = manifest["components"].as_array().unwrap();
+            assert_eq!(components.len(), blob.components.len());
+            assert_eq!(components[0]["trailer"]["terminator"], "DRSWMAI");
+            assert_eq!(components[0]["trailer"]["model"], "R8");
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn reads_from_file_and_reader() {
+    let blob = full_spec().build();
+    let path = temp_path("from-file");
+    fs::write(&path, &blob.data).unwrap();
+    let mut from_file = UnidenFirmware::from(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    from_file.read_buffer().unwrap();
+
+    let mut from_reader = UnidenFirmware::from_reader(blob.data.as_slice()).unwrap();
+    from_reader.read_buffer().unwrap();
+
+    let names = |firmware: &UnidenFirmware| {
+        firmware
+            .files()
+            .iter()
+            .map(|file| (file.kind().to_name(), file.info().base().offset()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&from_file), names(&parse(&blob.data)));
+    assert_eq!(names(&from_reader), names(&parse(&blob.data)));
+}
+
+#[test]
+fn component_views_borrow_from_blob() {
+    let spec = full_spec();
+    let blob = spec.build();
+    let firmware = parse(&blob.data);
+
+    let views: Vec<_> = firmware.components().collect();
+    assert_eq!(views.len(), blob.components.len());
+    for ((view, synth), component) in views.iter().zip(&blob.components).zip(&spec.components) {
+        assert_eq!(view.kind().to_name(), synth.name);
+        assert_eq!(view.tag().is_some(), synth.tag_offset.is_some());
+        assert_eq!(view.version(), component.version);
+        assert_eq!(
+            view.payload(),
+            &blob.data[synth.offset..synth.offset + synth.length]
+        );
+        assert_eq!(
+            view.trailer().unwrap().offset() as usize,
+            synth.trailer_offset
+        );
+
+        let key = match (synth.name, component.country.old_enc_key()) {
+            ("sound_dbnu" | "sound_dbla1" | "sound_dbla2", _) => Some(255),
+            ("gps_db" | "gps_db_second", Some((key, _))) => Some(key),
+            _ => None,
+        };
+        match (view.decoded(), key) {
+            (Some(decoded), Some(key)) => {
+                let body = &view.payload()[..decoded.len()];
+                assert_eq!(decoded.len(), (synth.length - 12) & !3);
+                assert_eq!(encode_old_model(key, &decoded, 0, decoded.len()), body);
+            }
+            (None, None) => {}
+            _ => panic!("{} decoded unexpectedly", synth.name),
+        }
+    }
+    assert_eq!(views[4].tag(), Some("STUI"));
+    assert!(views[0].tag().is_none());
+}
@@ -0,0 +1,149 @@
+#![cfg(feature = "python")]
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use pyo3::wrap_pymodule;
+use ufwt::format::{decode_old_model, GpsDbCountry, RDModel, SOUND_DB_KEY};
+use ufwt::synth::{payload, BlobSpec, ComponentSpec};
+
+fn with_module(test: impl FnOnce(&Bound<'_, PyModule>)) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let module = wrap_pymodule!(ufwt::python::ufwt)(py);
+        test(module.bind(py).downcast().unwrap());
+    });
+}
+
+fn bytes(value: Bound<'_, PyAny>) -> Vec<u8> {
+    value.downcast::<PyBytes>().unwrap().as_bytes().to_vec()
+}
+
+fn item<'py>(dict: &Bound<'py, PyDict>, key: &str) -> Bound<'py, PyAny> {
+    dict.get_item(key).unwrap().unwrap()
+}
+
+#[test]
+fn parses_blobs_into_dicts() {
+    let blob = BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("sound_dbnu", 112, 7))
+        .with(ComponentSpec::new("ble", 100, 21))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Us, 4))
+        .build();
+
+    with_module(|module| {
+        let parsed = module
+            .getattr("parse")
+            .unwrap()
+            .call1((PyBytes::new(module.py(), &blob.data),))
+            .unwrap();
+        let parsed = parsed.downcast::<PyDict>().unwrap();
+        assert_eq!(item(parsed, "model").extract::<String>().unwrap(), "R8");
+        assert_eq!(
+            item(parsed, "generation").extract::<String>().unwrap(),
+            "merged"
+        );
+        assert_eq!(item(parsed, "format_version").extract::<i32>().unwrap(), 3);
+        assert!(item(parsed, "diagnostics")
+            .downcast::<PyList>()
+            .unwrap()
+            .is_empty());
+
+        let components = item(parsed, "components");
+        let components = components.downcast::<PyList>().unwrap();
+        let kinds: Vec<String> = components
+            .iter()
+            .map(|component| {
+                let component = component.downcast::<PyDict>().unwrap().clone();
+                item(&component, "kind").extract().unwrap()
+            })
+            .collect();
+        assert_eq!(kinds, ["ui_nu", "sound_dbnu", "ble", "gps_db"]);
+
+        let component = |index: usize| {
+            components
+                .get_item(index)
+                .unwrap()
+                .downcast::<PyDict>()
+                .unwrap()
+                .clone()
+        };
+        // padded components are given trimmed, with nothing to decode
+        let ui = component(0);
+        let ui_synth = blob.component("ui_nu").unwrap();
+        assert_eq!(bytes(item(&ui, "data")), payload("ui_nu", 1000));
+        assert_eq!(bytes(item(&ui, "padded")).len(), 1024);
+        assert_eq!(
+            item(&ui, "offset").extract::<u64>().unwrap(),
+            ui_synth.offset as u64
+        );
+        assert_eq!(item(&ui, "version").extract::<i32>().unwrap(), 11);
+        assert!(item(&ui, "tag").is_none());
+        assert!(item(&ui, "decoded").is_none());
+        assert!(!item(&ui, "damaged").extract::<bool>().unwrap());
+        assert_eq!(
+            item(&component(2), "tag").extract::<String>().unwrap(),
+            "BLES"
+        );
+
+        // sound and GPS DBs come with their body decoded
+        let sound = component(1);
+        let data = bytes(item(&sound, "data"));
+        assert_eq!(data.len(), 112);
+        assert_eq!(
+            bytes(item(&sound, "decoded")),
+            decode_old_model(SOUND_DB_KEY, &data, 0, 100)
+        );
+        let gps = component(3);
+        assert_eq!(item(&gps, "country").extract::<String>().unwrap(), "US");
+        assert_eq!(item(&gps, "poi").extract::<i32>().unwrap(), 4);
+        assert_eq!(bytes(item(&gps, "decoded")).len(), 64);
+
+        let error = module
+            .getattr("parse")
+            .unwrap()
+            .call1((PyBytes::new(module.py(), &blob.data[..600]),))
+            .unwrap_err();
+        assert!(error.is_instance_of::<pyo3::exceptions::PyOSError>(module.py()));
+    });
+}
+
+#[test]
+fn checks_old_model_ranges() {
+    with_module(|module| {
+        let py = module.py();
+        let data = PyBytes::new(py, &(0..16).collect::<Vec<u8>>());
+        let call = |name: &str, offset: usize, length: Option<usize>| {
+            module
+                .getattr(name)
+                .unwrap()
+                .call1((SOUND_DB_KEY, &data, offset, length))
+        };
+
+        for name in ["decode_old_model", "encode_old_model"] {
+            // only the given range is returned, the rest of the data by default
+            assert_eq!(bytes(call(name, 4, Some(8)).unwrap()).len(), 8, "{}", name);
+            assert_eq!(bytes(call(name, 4, None).unwrap()).len(), 12, "{}", name);
+
+            for (offset, length, message) in [
+                (12, Some(8), "8 bytes at offset 12 are out of range"),
+                (17, None, "0 bytes at offset 17 are out of range"),
+                (
+                    1,
+                    Some(usize::MAX),
+                    "18446744073709551615 bytes at offset 1 are out of range",
+                ),
+            ] {
+                let error = call(name, offset, length).unwrap_err();
+                assert!(error.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+                assert_eq!(error.value(py).to_string(), message, "{}", name);
+            }
+        }
+
+        let encode = module.getattr("encode_old_model").unwrap();
+        let decode = module.getattr("decode_old_model").unwrap();
+        let encoded = encode.call1((SOUND_DB_KEY, &data)).unwrap();
+        let decoded = decode.call1((SOUND_DB_KEY, encoded)).unwrap();
+        assert_eq!(bytes(decoded), (0..16).collect::<Vec<u8>>());
+    });
+}
@@ -0,0 +1,140 @@
+use ufwt::carve::carve;
+use ufwt::file::{ExtractMode, UnidenFirmware};
+use ufwt::format::{GpsDbCountry, RDModel};
+use ufwt::lint::lint;
+use ufwt::synth::{BlobSpec, ComponentSpec, Mutation, SynthBlob};
+use ufwt::trailer::Trailer;
+
+/// xorshift, so failures can be reproduced from the iteration number.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn specs() -> Vec<BlobSpec> {
+    vec![
+        BlobSpec::merged(RDModel::R8, 3)
+            .with(ComponentSpec::new("ui_nu", 1000, 11))
+            .with(ComponentSpec::new("dsp_nu", 700, 12))
+            .with(ComponentSpec::new("gps_nu", 300, 13))
+            .with(ComponentSpec::new("sound_dbnu", 112, 7))
+            .with(ComponentSpec::new("ble", 100, 21))
+            .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Us, 4))
+            .with(ComponentSpec::new("sound_dbla2", 52, 5)),
+        BlobSpec::legacy(RDModel::R3)
+            .with(ComponentSpec::new("ui_nu", 10, 1))
+            .with(ComponentSpec::new("keypad", 20, 2))
+            .with(ComponentSpec::new("gps_db_second", 28, 3).gps_db(GpsDbCountry::Eu, 1)),
+        BlobSpec::package()
+            .with(ComponentSpec::new("gps_db", 28, 200).gps_db(GpsDbCountry::Nz, 1))
+            .with(ComponentSpec::new("gps_db_second", 12, 201)),
+    ]
+}
+
+fn interesting_length(rng: &mut Rng) -> i32 {
+    const LENGTHS: [i32; 10] = [0, 1, 11, 12, 511, 512, -1, -12, i32::MAX, i32::MIN];
+    match rng.below(3) {
+        0 => LENGTHS[rng.below(LENGTHS.len())],
+        1 => rng.next() as i32,
+        _ => rng.below(0x2000) as i32,
+    }
+}
+
+fn random_mutation(rng: &mut Rng, blob: &SynthBlob) -> Mutation {
+    let component = rng.below(16);
+    match rng.below(7) {
+        0 => Mutation::Length {
+            component,
+            length: interesting_length(rng),
+        },
+        1 => {
+            const TAGS: [&[u8; 4]; 5] = [b"GPSD", b"SUSD", b"NMGF", b"ZZSD", b"BLES"];
+            Mutation::Tag {
+                component,
+                tag: *TAGS[rng.below(TAGS.len())],
+            }
+        }
+        2 => Mutation::Trailer {
+            component,
+            index: rng.below(32),
+            value: rng.next() as u8,
+        },
+        3 => Mutation::SoundDbFlag(rng.below(2) == 1),
+        4 => Mutation::Truncate(rng.below(blob.data.len() + 1)),
+        5 => Mutation::Insert {
+            offset: rng.below(blob.data.len() + 1),
+            bytes: vec![rng.next() as u8; rng.below(600)],
+        },
+        _ => Mutation::Byte {
+            offset: rng.below(blob.data.len() + 1),
+            value: rng.next() as u8,
+        },
+    }
+}
+
+/// Parse `data` both ways and use whatever came out, none of which may panic.
+fn exercise(data: &[u8]) {
+    for section in carve(data) {
+        assert!(
+            section.start <= section.end,
+            "{} in {} bytes",
+            section.name,
+            data.len()
+        );
+        assert!(section.end <= section.trailer_end && section.trailer_end <= data.len());
+    }
+    for lenient in [false, true] {
+        let mut firmware = UnidenFirmware::from_bytes(data);
+        firmware.lenient = lenient;
+        if let Err(e) = firmware.read_buffer() {
+            assert!(!lenient, "{} {:?}", e, &data[..data.len().min(32)]);
+            continue;
+        }
+        for file in firmware.files() {
+            for mode in [ExtractMode::Padded, ExtractMode::Trimmed, ExtractMode::Raw] {
+                firmware.file_slice(file, mode);
+            }
+            if let Some(trailer) = file.trailer() {
+                let (_, span) = Trailer::span(file.kind().to_name());
+                assert_eq!(trailer.raw().len(), span);
+            }
+        }
+        if lenient {
+            lint(&firmware);
+        }
+    }
+}
+
+#[test]
+fn truncated_blobs_do_not_panic() {
+    for spec in specs() {
+        let blob = spec.build();
+        for length in 0..blob.data.len() {
+            exercise(&blob.data[..length]);
+        }
+    }
+}
+
+#[test]
+fn mutated_blobs_do_not_panic() {
+    let specs = specs();
+    for iteration in 0..2000u64 {
+        let mut rng = Rng(iteration.wrapping_mul(0x9E3779B97F4A7C15) | 1);
+        let mut blob = specs[rng.below(specs.len())].build();
+        for _ in 0..1 + rng.below(3) {
+            let mutation = random_mutation(&mut rng, &blob);
+            blob.mutate(&mutation);
+        }
+        exercise(&blob.data);
+    }
+}
@@ -0,0 +1,52 @@
+use ufwt::file::FWFileKind;
+use ufwt::format::RDModel;
+#[cfg(feature = "serde")]
+use ufwt::{
+    file::UnidenFirmware,
+    format::GpsDbCountry,
+    synth::{BlobSpec, ComponentSpec},
+};
+
+#[cfg(feature = "serde")]
+#[test]
+fn serializes_metadata_with_names() {
+    let blob = BlobSpec::merged(RDModel::R8, 3)
+        .with(ComponentSpec::new("ui_nu", 1000, 11))
+        .with(ComponentSpec::new("gps_db", 76, 123).gps_db(GpsDbCountry::Us, 4))
+        .build();
+    let mut firmware = UnidenFirmware::from_bytes(&blob.data);
+    firmware.read_buffer().unwrap();
+    let metadata = serde_json::to_value(firmware.metadata.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        metadata,
+        serde_json::json!({"model": "R8", "generation": "merged", "format_version": 3})
+    );
+
+    let gps_db = firmware.find_file("gps_db").unwrap().kind();
+    let value = serde_json::to_value(gps_db).unwrap();
+    assert_eq!(value["gps_db"]["country"], "US");
+    assert_eq!(value["gps_db"]["file_type"], "old_enc");
+    let kind: FWFileKind = serde_json::from_value(value).unwrap();
+    assert_eq!(kind.to_name(), "gps_db");
+
+    let model: RDModel = serde_json::from_str("\"R3-PLUS\"").unwrap();
+    assert!(model == RDModel::R3Plus);
+    assert_eq!(serde_json::to_string(&RDModel::R8W).unwrap(), "\"R8W\"");
+}
+
+#[test]
+fn parses_names_and_tags() {
+    assert!("r8nz".parse::<RDModel>() == Ok(RDModel::R8Nz));
+    assert!("UNKNOWN".parse::<RDModel>() == Ok(RDModel::Unknown));
+    assert!("R9".parse::<RDModel>().is_err());
+
+    for (tag, name, _) in ufwt::format::SECTION_TAGS {
+        assert_eq!(tag.parse::<FWFileKind>().unwrap().to_name(), name);
+        assert_eq!(name.parse::<FWFileKind>().unwrap().to_name(), name);
+    }
+    assert_eq!("ui_nu".parse::<FWFileKind>().unwrap().to_name(), "ui_nu");
+    assert_eq!(
+        "NMGF".parse::<FWFileKind>().err().unwrap().to_string(),
+        "Unknown component name"
+    );
+}
@@ -0,0 +1,202 @@
+#[cfg(feature = "serde")]
+use std::{fs, path::PathBuf};
+
+use ufwt::format::{decode_old_model, encode_old_model, SOUND_DB_KEY};
+use ufwt::sound::{SampleFormat, SoundDb};
+#[cfg(feature = "serde")]
+use ufwt::{
+    file::{ExtractMode, UnidenFirmware},
+    format::RDModel,
+    synth::{BlobSpec, ComponentSpec},
+};
+
+fn sine(len: usize, period: f64, amplitude: f64) -> Vec<i16> {
+    (0..len)
+        .map(|i| ((i as f64 * std::f64::consts::TAU / period).sin() * amplitude) as i16)
+        .collect()
+}
+
+#[test]
+fn round_trips_sound_db() {
+    for format in [
+        SampleFormat::Pcm8,
+        SampleFormat::Pcm16,
+        SampleFormat::ImaAdpcm,
+    ] {
+        let db = SoundDb {
+            version: 517,
+            format,
+            sample_rate: 8000,
+            prompts: vec![
+                format.encode(&sine(101, 40.0, 8000.0)),
+                vec![],
+                format.encode(&sine(33, 16.0, 3000.0)),
+            ],
+        };
+        let data = db.build();
+        assert_eq!(data.len() % 4, 0);
+
+        let parsed = SoundDb::parse(&data).unwrap();
+        assert_eq!(parsed.version, 517);
+        assert_eq!(parsed.format, format);
+        assert_eq!(parsed.sample_rate, 8000);
+        assert_eq!(parsed.prompts, db.prompts);
+        assert_eq!(parsed.build(), data);
+
+        // the version has to fit in the 10 bits of the trailer block
+        let db = SoundDb {
+            version: 0x400,
+            ..db
+        };
+        assert_eq!(
+            db.build_section("sound_dbnu").err().unwrap().to_string(),
+            "Version 1024 does not fit in 10 bits"
+        );
+    }
+
+    // a DB not following the assumed layout is rejected rather than misread
+    let db = |sample_rate| SoundDb {
+        version: 1,
+        format: SampleFormat::Pcm16,
+        sample_rate,
+        prompts: vec![vec![1, 2, 3, 4]; 2],
+    };
+    let mut swapped = db(8000).build();
+    let mut body = decode_old_model(SOUND_DB_KEY, &swapped, 0, 28);
+    body.swap(12, 20); // the table now lists the second prompt first
+    swapped[..28].copy_from_slice(&encode_old_model(SOUND_DB_KEY, &body, 0, 28));
+    for (data, message) in [
+        (db(0).build(), "Sample rate of 0 Hz"),
+        (
+            swapped,
+            "Prompt 0 at 0x20 (4 bytes) does not follow the previous one at 0x1c",
+        ),
+    ] {
+        assert_eq!(
+            SoundDb::parse(&data).err().unwrap().to_string(),
+            format!(
+                "{}, the sound DB does not follow the assumed prompt table layout",
+                message
+            )
+        );
+    }
+}
+
+#[test]
+fn encodes_and_decodes_ima_adpcm() {
+    let samples = sine(401, 50.0, 12000.0);
+    let encoded = SampleFormat::ImaAdpcm.encode(&samples);
+    // 4-byte preamble, then two samples per byte
+    assert_eq!(encoded.len(), 4 + 201);
+    assert_eq!(
+        encoded[..4],
+        [
+            samples[0].to_le_bytes()[0],
+            samples[0].to_le_bytes()[1],
+            0,
+            1
+        ]
+    );
+
+    let db = SoundDb {
+        version: 0,
+        format: SampleFormat::ImaAdpcm,
+        sample_rate: 8000,
+        prompts: vec![encoded],
+    };
+    let decoded = db.samples(0);
+    assert_eq!(decoded.len(), samples.len());
+    let error = samples
+        .iter()
+        .zip(&decoded)
+        .skip(20)
+        .map(|(a, b)| (*a as i32 - *b as i32).abs())
+        .max()
+        .unwrap();
+    assert!(error < 600, "error of {}", error);
+
+    // an even number of samples fills the last byte
+    let even = SampleFormat::ImaAdpcm.encode(&samples[..400]);
+    assert_eq!((even.len(), even[3]), (4 + 200, 0));
+    let db = SoundDb {
+        prompts: vec![even],
+        ..db
+    };
+    assert_eq!(db.samples(0).len(), 400);
+
+    // a stream too short for its preamble holds no samples
+    let db = SoundDb {
+        prompts: vec![vec![0; 3]],
+        ..db
+    };
+    assert!(db.samples(0).is_empty());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn builds_and_inserts_sound_db() {
+    let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("sound_db");
+    let _ = fs::remove_dir_all(&directory);
+    let db = SoundDb {
+        version: 12,
+        format: SampleFormat::Pcm16,
+        sample_rate: 11025,
+        prompts: vec![
+            SampleFormat::Pcm16.encode(&sine(300, 25.0, 9000.0)),
+            SampleFormat::Pcm16.encode(&sine(77, 10.0, 2000.0)),
+        ],
+    };
+    let index = db.write_to(&directory).unwrap();
+    assert_eq!(index.prompts.len(), 2);
+
+    // 16-bit prompts come back from their WAV files unchanged
+    let loaded = SoundDb::from_index(&directory.join("index.json")).unwrap();
+    assert_eq!(loaded.version, 12);
+    assert_eq!(loaded.prompts, db.prompts);
+
+    let mut blob = UnidenFirmware::from_bytes(
+        &BlobSpec::merged(RDModel::R8, 3)
+            .with(ComponentSpec::new("ui_nu", 1000, 11))
+            .with(ComponentSpec::new("ble", 100, 21))
+            .build()
+            .data,
+    );
+    blob.read_buffer().unwrap();
+    let section = loaded.build_section("sound_dbla2").unwrap();
+    assert!(section.ends_with(b"\0\0DRSWSUS"));
+    let out = blob
+        .insert_section("SUSD", "sound_dbla2", &section)
+        .unwrap();
+
+    let mut rebuilt = UnidenFirmware::from_bytes(&out);
+    rebuilt.read_buffer().unwrap();
+    let file = rebuilt.find_file("sound_dbla2").unwrap();
+    assert_eq!(file.info().base().version(), 12);
+    let parsed = SoundDb::parse(rebuilt.file_slice(file, ExtractMode::Trimmed)).unwrap();
+    assert_eq!(parsed.sample_rate, 11025);
+    assert_eq!(parsed.prompts, db.prompts);
+    assert!(loaded.build_section("ble").is_err());
+
+    // an out of range index version or a WAV without a sample rate is refused
+    let json = fs::read_to_string(directory.join("index.json")).unwrap();
+    fs::write(
+        directory.join("index.json"),
+        json.replace("\"version\": 12", "\"version\": 1024"),
+    )
+    .unwrap();
+    let error = SoundDb::from_index(&directory.join("index.json"))
+        .err()
+        .unwrap();
+    assert_eq!(error.to_string(), "Version 1024 does not fit in 10 bits");
+
+    fs::write(directory.join("index.json"), json).unwrap();
+    let wav = directory.join("001.wav");
+    let mut data = fs::read(&wav).unwrap();
+    // sample rate and byte rate of the fmt chunk
+    data[24..32].copy_from_slice(&[0; 8]);
+    fs::write(&wav, data).unwrap();
+    let error = SoundDb::from_index(&directory.join("index.json"))
+        .err()
+        .unwrap();
+    assert!(error.to_string().ends_with("001.wav: sample rate of 0 Hz"));
+}
@@ -0,0 +1,118 @@
+use core::fmt;
+
+/// Why a BLOB couldn't be parsed. `offset` is where the header, section tag or,
+/// for sections described by the header, payload being read starts.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The BLOB ends before the header, a tag or a trailer
+    Truncated { offset: usize },
+    /// A length that is negative, too short for a DB, or can't be padded
+    InvalidLength {
+        offset: usize,
+        name: &'static str,
+        length: i32,
+    },
+    /// A length of an unknown section that can't be skipped
+    InvalidUnknownLength {
+        offset: usize,
+        tag: [u8; 4],
+        length: i32,
+    },
+    /// The trailer block of a GPS DB has an unknown identify string
+    MalformedGpsDb { offset: usize },
+    /// Something other than the terminator of component `name` is at
+    /// `terminator`, `offset` being the start of the payload
+    WrongTerminator {
+        offset: usize,
+        name: &'static str,
+        terminator: usize,
+        found: [u8; 7],
+    },
+    /// An unknown section whose guessed length leads nowhere sensible
+    UnknownTag { offset: usize, tag: [u8; 4] },
+    /// An `NMGF` record before the end of the BLOB
+    MisplacedMergeRecord { offset: usize },
+    /// The last section reaches into the `NMGF` record at `offset`
+    MergeRecordOverlap { offset: usize },
+}
+
+impl Error {
+    pub fn offset(&self) -> usize {
+        match *self {
+            Error::Truncated { offset }
+            | Error::InvalidLength { offset, .. }
+            | Error::InvalidUnknownLength { offset, .. }
+            | Error::MalformedGpsDb { offset }
+            | Error::WrongTerminator { offset, .. }
+            | Error::UnknownTag { offset, .. }
+            | Error::MisplacedMergeRecord { offset }
+            | Error::MergeRecordOverlap { offset } => offset,
+        }
+    }
+}
+
+/// `bytes` the way `String::from_utf8_lossy` would print them.
+struct Lossy<'a>(&'a [u8]);
+
+impl fmt::Display for Lossy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.0.utf8_chunks() {
+            f.write_str(chunk.valid())?;
+            if !chunk.invalid().is_empty() {
+                f.write_str("\u{FFFD}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Lossy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"")?;
+        for chunk in self.0.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                match c {
+                    '\'' => f.write_str("'")?,
+                    c => write!(f, "{}", c.escape_debug())?,
+                }
+            }
+            if !chunk.invalid().is_empty() {
+                f.write_str("\u{FFFD}")?;
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Truncated { .. } => f.write_str("failed to fill whole buffer"),
+            Error::InvalidLength { name, length, .. } => {
+                write!(f, "Invalid {} length: {}", name, length)
+            }
+            Error::InvalidUnknownLength { tag, length, .. } => write!(
+                f,
+                "Invalid length of unknown section {:?}: {}",
+                Lossy(tag),
+                length
+            ),
+            Error::MalformedGpsDb { .. } => f.write_str("Malformed GPS DB File Info!"),
+            Error::WrongTerminator { name, found, .. } => write!(
+                f,
+                "Wrong termination sequence after {}: {}",
+                name,
+                Lossy(found)
+            ),
+            Error::UnknownTag { tag, .. } => write!(f, "Unknown section tag: {:?}", Lossy(tag)),
+            Error::MisplacedMergeRecord { .. } => {
+                f.write_str("NMGF record before the end of the BLOB")
+            }
+            Error::MergeRecordOverlap { .. } => {
+                f.write_str("Last section overlaps the NMGF record")
+            }
+        }
+    }
+}
+
+impl core::error::Error for Error {}
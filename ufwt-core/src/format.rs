@@ -0,0 +1,384 @@
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+use core::fmt;
+use core::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub const SOUND_DB_KEY: u8 = 255;
+pub const OLD_US_GPS_DB_KEY: u8 = 210;
+pub const OLD_NZ_GPS_DB_KEY: u8 = 194;
+pub const OLD_IL_GPS_DB_KEY: u8 = 226;
+
+pub const OLD_FILE_GPS_DB_IDENTIFY_STR: [&str; 3] = ["LRDB", "DFDB", "IRDB"];
+
+pub const NEW_FILE_GPS_DB_IDENTIFY_STR: [&str; 4] = ["AEUS", "AENZ", "AEIL", "AEEU"];
+
+/// Component name and terminator of the sections described by the BLOB header.
+pub const HEADER_SECTIONS: [(&str, &str); 4] = [
+    ("ui_nu", "DRSWMAI"),
+    ("dsp_nu", "DRSWDSP"),
+    ("gps_nu", "DRSWSUB"),
+    ("sound_dbnu", "DRSWSDB"),
+];
+
+/// Tag, component name and terminator of the tagged sections.
+pub const SECTION_TAGS: [(&str, &str, &str); 15] = [
+    ("GPSD", "gps_db", "DRSWGDB"),
+    ("GASD", "gps_db_second", "DRSWGAE"),
+    ("BLES", "ble", "DRSWBLE"),
+    ("KEYS", "keypad", "DRSWKEY"),
+    ("LSRS", "laser_if", "DRSWLSR"),
+    ("STUI", "ui_stm", "DRSWSTU"),
+    ("STDS", "dsp_stm", "DRSWSTD"),
+    ("STGP", "gps_stm", "DRSWSTG"),
+    ("N2UI", "ui_nu2", "DRSWN2U"),
+    ("N2DS", "dsp_nu2", "DRSWN2D"),
+    ("N3DS", "dsp_nu3", "DRSWN3D"),
+    ("N2GP", "gps_nu2", "DRSWN2G"),
+    ("N3GP", "gps_nu3", "DRSWN3G"),
+    ("STSD", "sound_dbla1", "DRSWSTS"),
+    ("SUSD", "sound_dbla2", "DRSWSUS"),
+];
+
+/// Terminator expected after component `name`.
+pub fn terminator_for_component(name: &str) -> Option<&'static str> {
+    HEADER_SECTIONS
+        .iter()
+        .map(|(name, terminator)| (*name, *terminator))
+        .chain(
+            SECTION_TAGS
+                .iter()
+                .map(|(_, name, terminator)| (*name, *terminator)),
+        )
+        .find(|(n, _)| *n == name)
+        .map(|(_, terminator)| terminator)
+}
+
+/// Component name of the section ending with `terminator`.
+pub fn component_for_terminator(terminator: &[u8]) -> Option<&'static str> {
+    HEADER_SECTIONS
+        .iter()
+        .map(|(name, terminator)| (*name, *terminator))
+        .chain(
+            SECTION_TAGS
+                .iter()
+                .map(|(_, name, terminator)| (*name, *terminator)),
+        )
+        .find(|(_, t)| t.as_bytes() == terminator)
+        .map(|(name, _)| name)
+}
+
+/// Number of skipped bytes and the terminator that follow the sound and GPS DB
+/// sections, which are stored unpadded with their own 12-byte trailer block.
+pub fn db_terminator(name: &str) -> Option<(usize, &'static str)> {
+    match name {
+        "sound_dbnu" => Some((0, "DRSWSDB")),
+        "sound_dbla1" => Some((0, "DRSWSTS")),
+        "sound_dbla2" => Some((2, "DRSWSUS")),
+        "gps_db" => Some((0, "DRSWGDB")),
+        "gps_db_second" => Some((2, "DRSWGAE")),
+        _ => None,
+    }
+}
+
+/// Bytes following the payload of component `name`: the model/version word and
+/// terminator, or the skipped bytes and terminator after a sound or GPS DB.
+pub fn trailer_length(name: &str) -> usize {
+    db_terminator(name).map_or(9, |(skip, terminator)| skip + terminator.len())
+}
+
+/// Block the payload of tagged section `name` is padded to, `None` for the
+/// unpadded sound and GPS DBs.
+pub fn padding_block(name: &str) -> Option<i32> {
+    match name {
+        _ if db_terminator(name).is_some() => None,
+        "ble" => Some(1024),
+        _ => Some(512),
+    }
+}
+
+/// Length of a section padded to 512 bytes, or `None` if that doesn't fit in an `i32`.
+#[inline(always)]
+pub fn alter_length(length: i32) -> Option<i32> {
+    // can also be written as (length & 0xfffffe00) + 512
+    if length != 0 {
+        (length / 512 + 1).checked_mul(512)
+    } else {
+        Some(length)
+    }
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GpsDbType {
+    #[cfg_attr(feature = "serde", serde(rename = "old_enc"))]
+    GpsDbOldEnc,
+    #[cfg_attr(feature = "serde", serde(rename = "aes128"))]
+    GpsDbAes128,
+    #[cfg_attr(feature = "serde", serde(rename = "unknown"))]
+    Unknown,
+}
+
+/// Serialized as [`GpsDbCountry::to_name`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "UPPERCASE"))]
+pub enum GpsDbCountry {
+    Us,
+    Nz,
+    Il,
+    Eu,
+}
+
+impl GpsDbCountry {
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            GpsDbCountry::Us,
+            GpsDbCountry::Nz,
+            GpsDbCountry::Il,
+            GpsDbCountry::Eu,
+        ]
+        .into_iter()
+        .find(|country| country.to_name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            GpsDbCountry::Us => "US",
+            GpsDbCountry::Nz => "NZ",
+            GpsDbCountry::Il => "IL",
+            GpsDbCountry::Eu => "EU",
+        }
+    }
+
+    /// Model reported for standalone GPS DB packages of this country.
+    pub fn db_model(&self) -> RDModel {
+        match self {
+            GpsDbCountry::Us => RDModel::DbUs,
+            GpsDbCountry::Nz => RDModel::DbNz,
+            GpsDbCountry::Il => RDModel::DbIl,
+            GpsDbCountry::Eu => RDModel::DbEu,
+        }
+    }
+
+    /// Key and identify string of the old encoding GPS DB, if there is one for
+    /// this country.
+    pub fn old_enc_key(&self) -> Option<(u8, &'static str)> {
+        match self {
+            GpsDbCountry::Us => Some((OLD_US_GPS_DB_KEY, OLD_FILE_GPS_DB_IDENTIFY_STR[0])),
+            GpsDbCountry::Nz => Some((OLD_NZ_GPS_DB_KEY, OLD_FILE_GPS_DB_IDENTIFY_STR[1])),
+            GpsDbCountry::Il => Some((OLD_IL_GPS_DB_KEY, OLD_FILE_GPS_DB_IDENTIFY_STR[2])),
+            GpsDbCountry::Eu => None,
+        }
+    }
+}
+
+/// Serialized as [`RDModel::to_name`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "UPPERCASE"))]
+#[repr(u8)]
+pub enum RDModel {
+    R1 = 1,
+    R3 = 3,
+    R3Nz = 4,
+    R3Nzk = 5,
+    #[cfg_attr(feature = "serde", serde(rename = "R3-PLUS"))]
+    R3Plus = 64,
+    #[cfg_attr(feature = "serde", serde(rename = "R3NZK-PLUS"))]
+    R3NzkPlus = 65,
+    R7 = 7,
+    R7Nz = 8,
+    R7Il = 9,
+    R4 = 14,
+    R4Nz = 15,
+    R4Il = 16,
+    R4Eu = 17,
+    R8 = 18,
+    R8Nz = 19,
+    R8Il = 20,
+    R8Eu = 21,
+    R4W = 24,
+    R8W = 28,
+    DbEu = 236,
+    DbIl = 237,
+    DbUs = 238,
+    DbNz = 239,
+    Unknown = 255,
+}
+
+impl From<u8> for RDModel {
+    fn from(item: u8) -> Self {
+        match item {
+            1 => RDModel::R1,
+            3 => RDModel::R3,
+            4 => RDModel::R3Nz,
+            5 => RDModel::R3Nzk,
+            64 => RDModel::R3Plus,
+            65 => RDModel::R3NzkPlus,
+            7 => RDModel::R7,
+            8 => RDModel::R7Nz,
+            9 => RDModel::R7Il,
+            14 => RDModel::R4,
+            15 => RDModel::R4Nz,
+            16 => RDModel::R4Il,
+            17 => RDModel::R4Eu,
+            18 => RDModel::R8,
+            19 => RDModel::R8Nz,
+            20 => RDModel::R8Il,
+            21 => RDModel::R8Eu,
+            24 => RDModel::R4W,
+            28 => RDModel::R8W,
+            236 => RDModel::DbEu,
+            237 => RDModel::DbIl,
+            238 => RDModel::DbUs,
+            239 => RDModel::DbNz,
+            _ => RDModel::Unknown,
+        }
+    }
+}
+
+impl RDModel {
+    pub fn from_data(data: i16) -> Self {
+        // mask the upper six bits from `data`
+        let model = ((data >> 10) & 0x3F) as u8;
+        model.into()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        (0..=u8::MAX)
+            .map(RDModel::from)
+            .find(|model| *model != RDModel::Unknown && model.to_name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            RDModel::R1 => "R1",
+            RDModel::R3 => "R3",
+            RDModel::R3Nz => "R3NZ",
+            RDModel::R3Nzk => "R3NZK",
+            RDModel::R3Plus => "R3-PLUS",
+            RDModel::R3NzkPlus => "R3NZK-PLUS",
+            RDModel::R7 => "R7",
+            RDModel::R7Nz => "R7NZ",
+            RDModel::R7Il => "R7IL",
+            RDModel::R4 => "R4",
+            RDModel::R4Nz => "R4NZ",
+            RDModel::R4Il => "R4IL",
+            RDModel::R4Eu => "R4EU",
+            RDModel::R8 => "R8",
+            RDModel::R8Nz => "R8NZ",
+            RDModel::R8Il => "R8IL",
+            RDModel::R8Eu => "R8EU",
+            RDModel::R4W => "R4W",
+            RDModel::R8W => "R8W",
+            RDModel::DbEu => "DBEU",
+            RDModel::DbIl => "DBIL",
+            RDModel::DbUs => "DBUS",
+            RDModel::DbNz => "DBNZ",
+            RDModel::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+impl FromStr for RDModel {
+    type Err = UnknownName;
+
+    /// Like [`RDModel::from_name`], also accepting `UNKNOWN`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match RDModel::from_name(name) {
+            Some(model) => Ok(model),
+            None if RDModel::Unknown.to_name().eq_ignore_ascii_case(name) => Ok(RDModel::Unknown),
+            None => Err(UnknownName("model")),
+        }
+    }
+}
+
+/// Error of the `FromStr` implementations, holding what was being parsed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UnknownName(pub &'static str);
+
+impl fmt::Display for UnknownName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown {} name", self.0)
+    }
+}
+
+impl core::error::Error for UnknownName {}
+
+#[inline(always)]
+pub fn rd_version(data: i16) -> i16 {
+    if data == -1 {
+        data
+    } else {
+        data & 0x3FF
+    }
+}
+
+/// Decode `data` into `out`, 4 bytes at a time. Whole words missing from
+/// `data` are left untouched.
+pub fn decode_old_model_into(key: u8, data: &[u8], out: &mut [u8]) {
+    for (out, word) in out.chunks_exact_mut(4).zip(data.chunks_exact(4)) {
+        out[0] = word[0] & 3;
+        out[0] += (word[1] & 3) << 2;
+        out[0] += (word[2] & 3) << 4;
+        out[0] += (word[3] & 3) << 6;
+
+        out[1] = (word[0] & 0xC) >> 2;
+        out[1] += word[1] & 0xC;
+        out[1] += (word[2] & 0xC) << 2;
+        out[1] += (word[3] & 0xC) << 4;
+
+        out[2] = (word[0] & 0x30) >> 4;
+        out[2] += (word[1] & 0x30) >> 2;
+        out[2] += word[2] & 0x30;
+        out[2] += (word[3] & 0x30) << 2;
+
+        out[3] = (word[0] & 0xC0) >> 6;
+        out[3] += (word[1] & 0xC0) >> 4;
+        out[3] += (word[2] & 0xC0) >> 2;
+        out[3] += word[3] & 0xC0;
+
+        for byte in out.iter_mut() {
+            *byte = byte.wrapping_sub(key);
+        }
+    }
+}
+
+/// Inverse of [`decode_old_model_into`]. `data` must cover all of `out`.
+pub fn encode_old_model_into(key: u8, data: &[u8], out: &mut [u8]) {
+    for (out, word) in out.chunks_exact_mut(4).zip(data.chunks_exact(4)) {
+        let plain = [
+            word[0].wrapping_add(key),
+            word[1].wrapping_add(key),
+            word[2].wrapping_add(key),
+            word[3].wrapping_add(key),
+        ];
+
+        for (j, byte) in out.iter_mut().enumerate() {
+            let shift = 2 * j;
+            *byte = (plain[0] >> shift) & 3;
+            *byte += ((plain[1] >> shift) & 3) << 2;
+            *byte += ((plain[2] >> shift) & 3) << 4;
+            *byte += ((plain[3] >> shift) & 3) << 6;
+        }
+    }
+}
+
+/// Decode `length` bytes of `data` starting at `offset`, 4 bytes at a time.
+/// Whole words missing from `data` are left as zeros.
+#[cfg(feature = "alloc")]
+pub fn decode_old_model(key: u8, data: &[u8], offset: usize, length: usize) -> Vec<u8> {
+    let mut buffer = vec![0u8; length];
+    decode_old_model_into(key, data.get(offset..).unwrap_or_default(), &mut buffer);
+    buffer
+}
+
+/// Inverse of [`decode_old_model`].
+#[cfg(feature = "alloc")]
+pub fn encode_old_model(key: u8, data: &[u8], offset: usize, length: usize) -> Vec<u8> {
+    let mut buffer = vec![0u8; length];
+    encode_old_model_into(key, &data[offset..offset + length], &mut buffer);
+    buffer
+}
@@ -0,0 +1,14 @@
+//! Parsing of Uniden firmware BLOBs held in memory, without the standard
+//! library. Everything works on borrowed slices; the `alloc` feature adds the
+//! helpers returning a `Vec`.
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod error;
+pub mod format;
+pub mod section;
+pub mod trailer;
+
+pub use error::Error;
@@ -0,0 +1,498 @@
+use crate::error::Error;
+use crate::format::{
+    alter_length, decode_old_model_into, padding_block, terminator_for_component, GpsDbCountry,
+    GpsDbType, HEADER_SECTIONS, OLD_IL_GPS_DB_KEY, OLD_NZ_GPS_DB_KEY, OLD_US_GPS_DB_KEY,
+    SECTION_TAGS,
+};
+use crate::trailer::Trailer;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Layout of a BLOB, deciding which parts the parser expects.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum FormatGeneration {
+    /// Header describing ui_nu, dsp_nu, gps_nu and sound_dbnu, possibly followed
+    /// by tagged sections, without an `NMGF` record
+    Legacy,
+    /// Legacy layout closed by an `NMGF` merge record holding the format version
+    Merged,
+    /// Tagged `GPSD`/`GASD` sections without a header
+    GpsDbPackage,
+}
+
+impl FormatGeneration {
+    /// Work out the generation from the first and last bytes of a BLOB.
+    pub fn detect(buffer: &[u8]) -> FormatGeneration {
+        if matches!(buffer.get(0..4), Some(b"GPSD") | Some(b"GASD")) {
+            FormatGeneration::GpsDbPackage
+        } else if merge_record(buffer).is_some() {
+            FormatGeneration::Merged
+        } else {
+            FormatGeneration::Legacy
+        }
+    }
+
+    pub fn to_name(&self) -> &'static str {
+        match self {
+            FormatGeneration::Legacy => "legacy",
+            FormatGeneration::Merged => "merged",
+            FormatGeneration::GpsDbPackage => "GPS DB package",
+        }
+    }
+
+    /// Whether the BLOB starts with the header describing the legacy sections.
+    pub fn has_header(&self) -> bool {
+        !matches!(self, FormatGeneration::GpsDbPackage)
+    }
+}
+
+/// Format version held by the `NMGF` record ending merged BLOBs.
+pub fn merge_record(buffer: &[u8]) -> Option<i32> {
+    let record = buffer.get(buffer.len().checked_sub(12)?..)?;
+    (&record[0..4] == b"NMGF").then(|| i32::from_le_bytes(record[8..12].try_into().unwrap()))
+}
+
+/// Declared lengths of the sections described by the BLOB header.
+#[derive(Clone, Copy, Debug)]
+pub struct Header {
+    pub ui_nu: i32,
+    pub dsp_nu: i32,
+    pub gps_nu: i32,
+    /// Only present if the sound DB flag is set, 0 otherwise
+    pub sound_dbnu: i32,
+    pub includes_sound_db: bool,
+}
+
+impl Header {
+    pub fn parse(data: &[u8]) -> Result<Header, Error> {
+        let word = |pos: usize| {
+            data.get(pos..pos + 4)
+                .map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap()))
+                .ok_or(Error::Truncated { offset: 0 })
+        };
+        let first = word(0)?;
+        let includes_sound_db = (first >> 0x18) & 0x1 == 1;
+        Ok(Header {
+            ui_nu: first & 0xFFFFFF,
+            dsp_nu: word(4)?,
+            gps_nu: word(8)?,
+            sound_dbnu: if includes_sound_db { word(20)? } else { 0 },
+            includes_sound_db,
+        })
+    }
+
+    /// Length of the header itself.
+    pub fn size(&self) -> usize {
+        if self.includes_sound_db {
+            24
+        } else {
+            12
+        }
+    }
+
+    /// Declared length of header section `name`, as in [`HEADER_SECTIONS`].
+    pub fn declared(&self, name: &str) -> Option<i32> {
+        match name {
+            "ui_nu" => Some(self.ui_nu),
+            "dsp_nu" => Some(self.dsp_nu),
+            "gps_nu" => Some(self.gps_nu),
+            "sound_dbnu" => Some(self.sound_dbnu),
+            _ => None,
+        }
+    }
+}
+
+/// The 12-byte trailer block ending a GPS DB: POI count, version and identify string.
+#[derive(Clone, Copy)]
+pub struct GpsDbInfo {
+    pub poi: i32,
+    pub version: i32,
+    pub file_type: GpsDbType,
+    pub country: Option<GpsDbCountry>,
+}
+
+impl GpsDbInfo {
+    /// Decode `block`, returning `None` if the identify string is unknown.
+    pub fn parse(block: &[u8]) -> Option<GpsDbInfo> {
+        let block: &[u8; 12] = block.get(0..12)?.try_into().unwrap();
+        let word = |bytes: &[u8]| i32::from_le_bytes(bytes.try_into().unwrap());
+
+        let old_key = match &block[8..12] {
+            b"LRDB" => Some((OLD_US_GPS_DB_KEY, GpsDbCountry::Us)),
+            b"DFDB" => Some((OLD_NZ_GPS_DB_KEY, GpsDbCountry::Nz)),
+            b"IRDB" => Some((OLD_IL_GPS_DB_KEY, GpsDbCountry::Il)),
+            _ => None,
+        };
+        let (file_type, country, poi) = match old_key {
+            Some((key, country)) => {
+                let mut poi = [0; 4];
+                decode_old_model_into(key, &block[0..4], &mut poi);
+                (GpsDbType::GpsDbOldEnc, country, i32::from_le_bytes(poi))
+            }
+            None => {
+                let country = match &block[8..12] {
+                    b"AEUS" => GpsDbCountry::Us,
+                    b"AENZ" => GpsDbCountry::Nz,
+                    b"AEIL" => GpsDbCountry::Il,
+                    b"AEEU" => GpsDbCountry::Eu,
+                    _ => return None,
+                };
+                (GpsDbType::GpsDbAes128, country, word(&block[0..4]))
+            }
+        };
+
+        Some(GpsDbInfo {
+            poi,
+            version: word(&block[4..8]),
+            file_type,
+            country: Some(country),
+        })
+    }
+}
+
+/// Component name of the section tagged `tag`.
+pub fn component_for_tag(tag: &[u8]) -> Option<&'static str> {
+    SECTION_TAGS
+        .iter()
+        .find(|(t, _, _)| t.as_bytes() == tag)
+        .map(|(_, name, _)| *name)
+}
+
+pub fn is_known_tag(tag: &[u8]) -> bool {
+    tag == b"NMGF" || component_for_tag(tag).is_some()
+}
+
+/// Whether a section tag (or the `NMGF` record) that fits in `buffer` starts at `pos`.
+pub fn is_section_start(buffer: &[u8], pos: usize) -> bool {
+    let Some(tag) = buffer.get(pos..pos + 4) else {
+        return false;
+    };
+    if tag == b"NMGF" {
+        return pos + 12 == buffer.len();
+    }
+    if !is_known_tag(tag) {
+        return false;
+    }
+    let Some(length) = buffer.get(pos + 8..pos + 12) else {
+        return false;
+    };
+    let length = u32::from_le_bytes(length.try_into().unwrap());
+    pos + 12 + length as usize <= buffer.len()
+}
+
+/// A component found by [`Sections`], borrowing from the BLOB.
+#[derive(Clone, Copy)]
+pub struct Section<'a> {
+    pub name: &'static str,
+    /// Start of the section tag, `None` for the sections described by the header
+    pub tag_offset: Option<usize>,
+    /// Start of the payload
+    pub offset: usize,
+    /// The payload including its padding, and the trailer block of DBs
+    pub payload: &'a [u8],
+    /// Length as declared in the header or section tag
+    pub declared_length: i32,
+    pub trailer: Trailer<'a>,
+    /// Trailer block of GPS DBs
+    pub gps_db: Option<GpsDbInfo>,
+}
+
+/// Iterator over the components of a BLOB held in memory, without allocating.
+///
+/// Unknown sections are skipped. After an error the iterator stops, unless
+/// [`Sections::seek`] tells it where to carry on.
+pub struct Sections<'a> {
+    data: &'a [u8],
+    generation: FormatGeneration,
+    format_version: Option<i32>,
+    /// Tagged sections run up to the `NMGF` record, if there is one
+    body_end: usize,
+    position: usize,
+    header: Option<Header>,
+    /// Index into [`HEADER_SECTIONS`] of the next section to read
+    next_header_section: usize,
+    pending: Option<(&'static str, usize)>,
+    verify_unknown_tags: bool,
+    stopped: bool,
+    finished: bool,
+}
+
+impl<'a> Sections<'a> {
+    pub fn new(data: &'a [u8]) -> Sections<'a> {
+        let generation = FormatGeneration::detect(data);
+        let format_version = merge_record(data);
+        Sections {
+            data,
+            generation,
+            format_version,
+            body_end: data.len() - if format_version.is_some() { 12 } else { 0 },
+            position: 0,
+            header: None,
+            next_header_section: if generation.has_header() {
+                0
+            } else {
+                HEADER_SECTIONS.len()
+            },
+            pending: None,
+            verify_unknown_tags: false,
+            stopped: false,
+            finished: false,
+        }
+    }
+
+    /// Fail with [`Error::UnknownTag`] when skipping an unknown section doesn't
+    /// lead to another section or the end of the BLOB.
+    pub fn verify_unknown_tags(mut self, verify: bool) -> Sections<'a> {
+        self.verify_unknown_tags = verify;
+        self
+    }
+
+    pub fn generation(&self) -> FormatGeneration {
+        self.generation
+    }
+
+    /// `NMGF` format version, only present in merged BLOBs.
+    pub fn format_version(&self) -> Option<i32> {
+        self.format_version
+    }
+
+    /// The header, once it has been read.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Name and payload offset of the known section that failed to read.
+    pub fn pending(&self) -> Option<(&'static str, usize)> {
+        self.pending
+    }
+
+    /// Carry on reading at `position`, e.g. after recovering from an error.
+    pub fn seek(&mut self, position: usize) {
+        self.position = position;
+        self.pending = None;
+        self.stopped = false;
+    }
+
+    /// Give up on the rest of the sections described by the header, and only
+    /// look for tagged sections from now on.
+    pub fn skip_header(&mut self) {
+        self.next_header_section = HEADER_SECTIONS.len();
+    }
+
+    /// Like [`Iterator::next`], reporting every range of the BLOB that was
+    /// read to `on_read` as `(start, end)`.
+    pub fn next_with(
+        &mut self,
+        on_read: &mut dyn FnMut(usize, usize),
+    ) -> Option<Result<Section<'a>, Error>> {
+        if self.stopped || self.finished {
+            return None;
+        }
+        let result = self.read_next(on_read)?;
+        if result.is_err() && !self.finished {
+            self.stopped = true;
+        }
+        Some(result)
+    }
+
+    fn read_next(
+        &mut self,
+        on_read: &mut dyn FnMut(usize, usize),
+    ) -> Option<Result<Section<'a>, Error>> {
+        if self.header.is_none() && self.next_header_section == 0 {
+            let header = match Header::parse(self.data) {
+                Ok(header) => header,
+                Err(error) => {
+                    self.skip_header();
+                    return Some(Err(error));
+                }
+            };
+            on_read(0, header.size());
+            self.position = header.size();
+            self.header = Some(header);
+        }
+
+        while let Some((name, _)) = HEADER_SECTIONS.get(self.next_header_section) {
+            self.next_header_section += 1;
+            let declared = self.header.as_ref().and_then(|h| h.declared(name));
+            match declared {
+                Some(0) | None => continue,
+                Some(declared) => return Some(self.read_header_section(name, declared, on_read)),
+            }
+        }
+
+        while self.position < self.body_end {
+            let start = self.position;
+            let Some(tag) = self.data.get(start..start + 12) else {
+                return Some(Err(Error::Truncated { offset: start }));
+            };
+            on_read(start, start + 12);
+            self.position = start + 12;
+            let length = i32::from_le_bytes(tag[8..12].try_into().unwrap());
+
+            if &tag[0..4] == b"NMGF" {
+                // the record ending merged BLOBs is read separately
+                return Some(Err(Error::MisplacedMergeRecord { offset: start }));
+            }
+            if let Some(name) = component_for_tag(&tag[0..4]) {
+                return Some(self.read_tagged_section(name, start, length, on_read));
+            }
+            if let Err(error) = self.skip_unknown(start, tag[0..4].try_into().unwrap(), length) {
+                return Some(Err(error));
+            }
+        }
+
+        self.finished = true;
+        if self.format_version.is_some() {
+            on_read(self.body_end, self.data.len());
+            if self.position != self.body_end {
+                return Some(Err(Error::MergeRecordOverlap {
+                    offset: self.body_end,
+                }));
+            }
+        }
+        None
+    }
+
+    fn read_header_section(
+        &mut self,
+        name: &'static str,
+        declared: i32,
+        on_read: &mut dyn FnMut(usize, usize),
+    ) -> Result<Section<'a>, Error> {
+        let offset = self.position;
+        self.pending = Some((name, offset));
+        let invalid = Error::InvalidLength {
+            offset,
+            name,
+            length: declared,
+        };
+        let length = if name == "sound_dbnu" {
+            Some(declared).filter(|length| *length >= 12)
+        } else {
+            alter_length(declared).filter(|_| declared >= 0)
+        };
+        let length = length.ok_or(invalid)?;
+        self.read_section(name, None, offset, length, declared, on_read)
+    }
+
+    fn read_tagged_section(
+        &mut self,
+        name: &'static str,
+        start: usize,
+        declared: i32,
+        on_read: &mut dyn FnMut(usize, usize),
+    ) -> Result<Section<'a>, Error> {
+        let offset = self.position;
+        self.pending = Some((name, offset));
+        let invalid = Error::InvalidLength {
+            offset: start,
+            name,
+            length: declared,
+        };
+        let length = match padding_block(name) {
+            _ if declared < 0 => None,
+            None if declared < 12 => None,
+            None => Some(declared),
+            Some(block) => (declared / block + 1).checked_mul(block),
+        };
+        let length = length.ok_or(invalid)?;
+        self.read_section(name, Some(start), offset, length, declared, on_read)
+    }
+
+    /// Read the `length` bytes of component `name` starting at `offset`, and
+    /// the trailer after them.
+    fn read_section(
+        &mut self,
+        name: &'static str,
+        tag_offset: Option<usize>,
+        offset: usize,
+        length: i32,
+        declared_length: i32,
+        on_read: &mut dyn FnMut(usize, usize),
+    ) -> Result<Section<'a>, Error> {
+        let truncated = Error::Truncated {
+            offset: tag_offset.unwrap_or(offset),
+        };
+        let (inside, span) = Trailer::span(name);
+        let end = offset.checked_add(length as usize).ok_or(truncated)?;
+        let trailer_offset = end - inside;
+        let bytes = trailer_offset
+            .checked_add(span)
+            .and_then(|trailer_end| self.data.get(trailer_offset..trailer_end))
+            .ok_or(truncated)?;
+        on_read(trailer_offset, trailer_offset + span);
+        self.position = trailer_offset + span;
+        let trailer = Trailer::parse(name, bytes, trailer_offset).unwrap();
+
+        let gps_db = if name.starts_with("gps_db") {
+            let malformed = Error::MalformedGpsDb {
+                offset: tag_offset.unwrap_or(offset),
+            };
+            Some(GpsDbInfo::parse(bytes).ok_or(malformed)?)
+        } else {
+            None
+        };
+
+        if terminator_for_component(name).map(str::as_bytes) != Some(trailer.terminator) {
+            return Err(Error::WrongTerminator {
+                offset,
+                name,
+                terminator: self.position - 7,
+                found: trailer.terminator.try_into().unwrap(),
+            });
+        }
+
+        self.pending = None;
+        Ok(Section {
+            name,
+            tag_offset,
+            offset,
+            payload: &self.data[offset..end],
+            declared_length,
+            trailer,
+            gps_db,
+        })
+    }
+
+    /// Skip a section with an unknown tag, guessing its length the way the
+    /// known sections are laid out.
+    fn skip_unknown(&mut self, start: usize, tag: [u8; 4], declared: i32) -> Result<(), Error> {
+        let length = if &tag[2..4] == b"SD" {
+            Some(declared)
+        } else {
+            alter_length(declared)
+        };
+        let length = length
+            .and_then(|length| usize::try_from(length).ok())
+            .ok_or(Error::InvalidUnknownLength {
+                offset: start,
+                tag,
+                length: declared,
+            })?;
+        self.position = self.position.saturating_add(length).saturating_add(9);
+
+        // only trust the guessed length of unknown sections if it leads somewhere sensible
+        if self.verify_unknown_tags
+            && self.position != self.data.len()
+            && !is_section_start(self.data, self.position)
+        {
+            return Err(Error::UnknownTag { offset: start, tag });
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for Sections<'a> {
+    type Item = Result<Section<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with(&mut |_, _| {})
+    }
+}
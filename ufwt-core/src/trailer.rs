@@ -0,0 +1,79 @@
+use crate::format::{
+    db_terminator, decode_old_model_into, rd_version, trailer_length, RDModel, SOUND_DB_KEY,
+};
+
+/// End of a component: the model/version word of padded sections or the
+/// 12-byte trailer block of sound and GPS DBs, any skipped bytes, and the
+/// `DRSWxxx` terminator.
+#[derive(Clone, Copy)]
+pub struct Trailer<'a> {
+    /// Position of the trailer in the BLOB
+    pub offset: usize,
+    /// The trailer as stored, up to and including the terminator
+    pub raw: &'a [u8],
+    /// Model bits of padded sections; sound and GPS DBs have none
+    pub model: Option<RDModel>,
+    pub version: i32,
+    /// Bytes skipped between the trailer block and the terminator of `GASD` and `SUSD`
+    pub extra: &'a [u8],
+    pub terminator: &'a [u8],
+}
+
+impl<'a> Trailer<'a> {
+    /// Part of the trailer inside the payload of component `name`, and the
+    /// whole length of its trailer.
+    ///
+    /// The 12-byte block of sound and GPS DBs counts towards their declared
+    /// length, while the word after padded sections doesn't.
+    pub fn span(name: &str) -> (usize, usize) {
+        let block = if db_terminator(name).is_some() { 12 } else { 0 };
+        (block, block + trailer_length(name))
+    }
+
+    /// Decode the trailer of component `name` from `bytes`, which must be
+    /// exactly as long as [`Trailer::span`] says. `offset` is where it starts.
+    pub fn parse(name: &str, bytes: &'a [u8], offset: usize) -> Option<Trailer<'a>> {
+        if bytes.len() != Trailer::span(name).1 {
+            return None;
+        }
+        let terminator = &bytes[bytes.len() - 7..];
+
+        let (model, version, extra) = match db_terminator(name) {
+            Some((skip, _)) => {
+                let version = if name.starts_with("gps_db") {
+                    i32::from_le_bytes(bytes[4..8].try_into().unwrap())
+                } else {
+                    let mut word = [0; 4];
+                    decode_old_model_into(SOUND_DB_KEY, &bytes[0..4], &mut word);
+                    rd_version(i32::from_le_bytes(word) as i16) as i32
+                };
+                (None, version, &bytes[12..12 + skip])
+            }
+            None => {
+                let word = i16::from_le_bytes(bytes[0..2].try_into().unwrap());
+                (
+                    Some(RDModel::from_data(word)),
+                    rd_version(word) as i32,
+                    &bytes[0..0],
+                )
+            }
+        };
+
+        Some(Trailer {
+            offset,
+            raw: bytes,
+            model,
+            version,
+            extra,
+            terminator,
+        })
+    }
+
+    /// Read the trailer of component `name` whose payload ends at `end` in `buffer`.
+    pub fn read(buffer: &'a [u8], name: &str, end: usize) -> Option<Trailer<'a>> {
+        let (inside, length) = Trailer::span(name);
+        let start = end.checked_sub(inside)?;
+        let bytes = buffer.get(start..start.checked_add(length)?)?;
+        Trailer::parse(name, bytes, start)
+    }
+}